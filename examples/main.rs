@@ -38,9 +38,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     }),
   };
 
-  let allowed = evaluator.evaluate(&input)?;
+  let decision = evaluator.evaluate(&input)?;
 
-  println!("allowed = {allowed}");
+  println!("decision = {decision:?}");
 
   Ok(())
 }
@@ -1,20 +1,24 @@
 use aws_iam::model::{ConditionOperator, ConditionOperatorQuantifier, GlobalConditionOperator, QString};
 
 use crate::{
-  expression::Expr,
+  expression::{Expr, Str},
+  extensions::TryMapExprIter,
   functions::Func,
   parser::Error,
-  values::{ConditionValue, Value},
+  values::{ConditionValue, Value, Version},
 };
 
 pub type Conditions = Vec<(ConditionOperator, Vec<CondPair>)>;
 pub type CondPair = (QString, Value<ConditionValue>);
 pub type OperatorFunc<'f> = &'f dyn Fn(Box<Expr>, Box<Expr>) -> Expr;
 pub type IdOperatorFunc<'f> = &'f dyn Fn(Expr) -> Expr;
+type Converter = fn(&ConditionValue) -> Result<Expr, Error>;
+
+const INPUT: &str = "input";
 
 trait Negatable {
   fn is_neg(&self) -> bool;
-  fn apply(&self, ctxvalue: Expr, polvalues: Vec<Expr>, negative: bool, expr: impl Fn(Expr, Expr) -> Expr) -> Result<Expr, Error>;
+  fn apply(&self, attr: &QString, polvalues: Vec<Expr>, negative: bool, expr: impl Fn(Expr, Expr) -> Expr) -> Result<Expr, Error>;
 }
 
 impl Negatable for ConditionOperator {
@@ -27,9 +31,11 @@ impl Negatable for ConditionOperator {
     )
   }
 
-  fn apply(&self, ctxvalue: Expr, polvalues: Vec<Expr>, negative: bool, expr: impl Fn(Expr, Expr) -> Expr) -> Result<Expr, Error> {
+  fn apply(&self, attr: &QString, polvalues: Vec<Expr>, negative: bool, expr: impl Fn(Expr, Expr) -> Expr) -> Result<Expr, Error> {
     use ConditionOperatorQuantifier::*;
 
+    let ctxvalues = Func::to_array(INPUT, &path(attr));
+
     match (self.quantifier.as_ref().unwrap(), negative) {
       (_, true) => {
         let outer_var = Expr::item();
@@ -37,23 +43,38 @@ impl Negatable for ConditionOperator {
 
         let inner = Expr::Every(inner_var.clone().boxed(), Expr::list(polvalues).boxed(), expr(inner_var, outer_var.clone()).boxed());
 
-        Ok(Expr::Every(outer_var.boxed(), Func::to_array(ctxvalue).boxed(), inner.boxed()))
+        Ok(Expr::Every(outer_var.boxed(), ctxvalues.boxed(), inner.boxed()))
       }
 
-      (ForAnyValue, false) => Ok(expr(Expr::AnyIn(Expr::list(polvalues).boxed()), Expr::AnyIn(Func::to_array(ctxvalue).boxed()))),
+      (ForAnyValue, false) => Ok(expr(Expr::AnyIn(Expr::list(polvalues).boxed()), Expr::AnyIn(ctxvalues.boxed()))),
 
       (ForAllValues, false) => {
         let condition = expr(Expr::AnyIn(Expr::list(polvalues).boxed()), Expr::item());
 
-        Ok(Expr::Every(Expr::item().boxed(), Func::to_array(ctxvalue).boxed(), condition.boxed()))
+        Ok(Expr::Every(Expr::item().boxed(), ctxvalues.boxed(), condition.boxed()))
       }
     }
   }
 }
 
-pub fn build_condition(operator: &ConditionOperator, condition: &[CondPair]) -> Result<Vec<Expr>, Error> {
+pub fn build_condition(version: Version, operator: &ConditionOperator, condition: &[CondPair]) -> Result<Vec<Expr>, Error> {
+  let exprs = build_operator(version, operator, condition)?;
+
+  if !operator.if_exists {
+    return Ok(exprs);
+  }
+
+  condition.iter().zip(exprs).map(|((attr, _), expr)| if_exists(attr, expr)).collect()
+}
+
+fn build_operator(version: Version, operator: &ConditionOperator, condition: &[CondPair]) -> Result<Vec<Expr>, Error> {
   use aws_iam::model::GlobalConditionOperator::*;
 
+  let to_str: Converter = match version {
+    Version::V2008 => to_literal,
+    Version::V2012 => to_str,
+  };
+
   match &operator.operator {
     Bool => condition.iter().try_fold(Vec::with_capacity(condition.len()), |mut acc, (attr, values)| {
       acc.push(match values.map(to_bool)? {
@@ -64,6 +85,17 @@ pub fn build_condition(operator: &ConditionOperator, condition: &[CondPair]) ->
       Ok::<_, Error>(acc)
     }),
 
+    Null => condition.iter().try_fold(Vec::with_capacity(condition.len()), |mut acc, (attr, values)| {
+      let key = Func::key_null(Expr::list(path(attr).map_expr(Expr::str)?));
+
+      acc.push(match values.map(to_bool)? {
+        Value::One(one) => Expr::Eq(key.boxed(), one.boxed()),
+        Value::Many(list) => Expr::Eq(Expr::AnyIn(Expr::list(list).boxed()).boxed(), key.boxed()),
+      });
+
+      Ok::<_, Error>(acc)
+    }),
+
     StringEquals => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::Eq(polvalue.boxed(), ctxvalue.boxed())),
     StringNotEquals => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::Ne(polvalue.boxed(), ctxvalue.boxed())),
     StringEqualsIgnoreCase => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::Eq(Func::lower(polvalue).boxed(), Func::lower(ctxvalue).boxed())),
@@ -71,12 +103,12 @@ pub fn build_condition(operator: &ConditionOperator, condition: &[CondPair]) ->
     StringLike => compare(operator, condition, to_str, Func::glob),
     StringNotLike => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::neg(Func::glob(polvalue, ctxvalue))),
 
-    NumericEquals => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Eq(polvalue.boxed(), ctxvalue.boxed())),
-    NumericNotEquals => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Ne(polvalue.boxed(), ctxvalue.boxed())),
-    NumericLessThan => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Lt(ctxvalue.boxed(), polvalue.boxed())),
-    NumericLessThanEquals => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Lte(ctxvalue.boxed(), polvalue.boxed())),
-    NumericGreaterThan => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Gt(ctxvalue.boxed(), polvalue.boxed())),
-    NumericGreaterThanEquals => compare(operator, condition, to_int, |polvalue, ctxvalue| Expr::Gte(ctxvalue.boxed(), polvalue.boxed())),
+    NumericEquals => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Eq(polvalue.boxed(), ctxvalue.boxed())),
+    NumericNotEquals => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Ne(polvalue.boxed(), ctxvalue.boxed())),
+    NumericLessThan => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Lt(ctxvalue.boxed(), polvalue.boxed())),
+    NumericLessThanEquals => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Lte(ctxvalue.boxed(), polvalue.boxed())),
+    NumericGreaterThan => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Gt(ctxvalue.boxed(), polvalue.boxed())),
+    NumericGreaterThanEquals => compare(operator, condition, to_number, |polvalue, ctxvalue| Expr::Gte(ctxvalue.boxed(), polvalue.boxed())),
 
     DateEquals => compare(operator, condition, to_str, |polvalue, ctxvalue| {
       Expr::Eq(Func::datetime(polvalue).boxed(), Func::datetime(ctxvalue).boxed())
@@ -97,13 +129,15 @@ pub fn build_condition(operator: &ConditionOperator, condition: &[CondPair]) ->
       Expr::Lte(Func::datetime(ctxvalue).boxed(), Func::datetime(polvalue).boxed())
     }),
 
+    BinaryEquals => compare(operator, condition, to_base64, |polvalue, ctxvalue| Expr::Eq(polvalue.boxed(), Func::to_base64(ctxvalue).boxed())),
+
     IpAddress => compare(operator, condition, to_str, Func::cidr_contains),
     NotIpAddress => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::neg(Func::cidr_contains(polvalue, ctxvalue))),
 
     ArnLike | ArnEquals => compare(operator, condition, to_str, Func::arn_like),
     ArnNotLike | ArnNotEquals => compare(operator, condition, to_str, |polvalue, ctxvalue| Expr::Neg(Func::arn_like(polvalue, ctxvalue).boxed())),
 
-    _ => Err(Error::UnsupportedFunction(format!("{:?}", operator.operator)))?,
+    _ => Err(Error::UnsupportedFunction(operator_name(operator)))?,
   }
 }
 
@@ -119,12 +153,12 @@ where
 
     acc.push(match values.map(&converter)? {
       Value::One(polvalue) => match operator.quantifier {
-        Some(_) => operator.apply(ctxvalue, vec![polvalue], is_neg, &build_expr)?,
+        Some(_) => operator.apply(attr, vec![polvalue], is_neg, &build_expr)?,
         None => build_expr(polvalue, ctxvalue),
       },
 
       Value::Many(polvalues) => match operator.quantifier {
-        Some(_) => operator.apply(ctxvalue, polvalues, is_neg, &build_expr)?,
+        Some(_) => operator.apply(attr, polvalues, is_neg, &build_expr)?,
         None => match is_neg {
           false => build_expr(Expr::AnyIn(Expr::list(polvalues).boxed()), ctxvalue),
           true => Expr::every(polvalues, |polvalue| Ok(build_expr(polvalue, ctxvalue.clone())))?,
@@ -156,6 +190,43 @@ fn to_str(s: &ConditionValue) -> Result<Expr, Error> {
   }
 }
 
+fn to_literal(s: &ConditionValue) -> Result<Expr, Error> {
+  use aws_iam::model::ConditionValue::*;
+
+  match &**s {
+    String(s) => Ok(Expr::Str(Str::literal(s))),
+    _ => Err(Error::InvalidType("string", format!("{s:?}")))?,
+  }
+}
+
+fn to_base64(s: &ConditionValue) -> Result<Expr, Error> {
+  use aws_iam::model::ConditionValue::*;
+
+  match &**s {
+    String(s) => {
+      let data = s.trim_end_matches('=');
+
+      if data.len() % 4 == 1 || s.len() - data.len() > 2 || !data.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/') {
+        Err(Error::InvalidType("base64", s.to_string()))?
+      }
+
+      Ok(Expr::Str(Str::literal(data)))
+    }
+    _ => Err(Error::InvalidType("base64", format!("{s:?}")))?,
+  }
+}
+
+fn to_number(s: &ConditionValue) -> Result<Expr, Error> {
+  use aws_iam::model::ConditionValue::*;
+
+  match &**s {
+    Float(f) => Expr::float(*f),
+    String(s) if s.parse::<i64>().is_err() => Expr::float(s.parse().map_err(|_| Error::InvalidType("number", s.to_string()))?),
+    Integer(_) | String(_) => to_int(s),
+    _ => Err(Error::InvalidType("number", format!("{s:?}")))?,
+  }
+}
+
 fn to_int(s: &ConditionValue) -> Result<Expr, Error> {
   use aws_iam::model::ConditionValue::*;
 
@@ -166,11 +237,42 @@ fn to_int(s: &ConditionValue) -> Result<Expr, Error> {
   }
 }
 
+fn if_exists(var: &QString, expr: Expr) -> Result<Expr, Error> {
+  let path = Expr::list(path(var).map_expr(Expr::str)?);
+
+  Ok(Expr::Every(Expr::var("_").boxed(), Func::if_exists(path).boxed(), expr.boxed()))
+}
+
+/// Path of a condition key within the input.
+///
+/// Key names are case-insensitive, so paths are lowercased to match the
+/// input normalized by [`Evaluator::evaluate()`](crate::Evaluator::evaluate()).
+fn path(var: &QString) -> Vec<String> {
+  var.qualifier().iter().map(String::as_str).chain(var.value().split('/')).map(str::to_lowercase).collect()
+}
+
+/// Name of a condition operator, as written in policy documents.
+///
+/// The `Display` implementation of `ConditionOperator` prints the `Debug`
+/// representation of unknown operators instead of their name.
+pub fn operator_name(operator: &ConditionOperator) -> String {
+  let quantifier = match &operator.quantifier {
+    Some(quantifier) => format!("{quantifier:?}:"),
+    None => String::new(),
+  };
+
+  let name = match &operator.operator {
+    GlobalConditionOperator::Other(name) => name.to_string(),
+    operator => format!("{operator:?}"),
+  };
+
+  let suffix = if operator.if_exists { "IfExists" } else { "" };
+
+  format!("{quantifier}{name}{suffix}")
+}
+
 fn resolve(var: &QString) -> Result<Expr, Error> {
-  Ok(match var.qualifier() {
-    Some(q) => Expr::var(format!("input.{}.{}", q, var.value().replace('/', "."))),
-    _ => Expr::var(format!("input.{}", var.to_string().replace('/', "."))),
-  })
+  Ok(Expr::path(INPUT, &path(var)))
 }
 
 #[cfg(test)]
@@ -179,18 +281,24 @@ mod tests {
 
   use crate::{
     conditions::build_condition,
-    expression::{Expr, Repr, Str},
-    values::{ConditionValue, Value},
+    expression::{Expr, Float, Repr, Str},
+    values::{ConditionValue, Value, Version},
   };
 
   #[test]
   fn bool_equals() {
-    let expr = build_condition(&ConditionOperator::new(Bool), &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::Bool(true))))]).unwrap();
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(Bool),
+      &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::Bool(true))))],
+    )
+    .unwrap();
 
     assert_eq!(expr.len(), 1);
     assert_eq!(expr[0].repr_to_string().unwrap(), r#"input.username == true"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(Bool),
       &[(
         QString::unqualified("username".into()),
@@ -206,6 +314,7 @@ mod tests {
   #[test]
   fn string_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals),
       &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::String("apognu".into()))))],
     )
@@ -215,6 +324,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#""apognu" == input.username"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals),
       &[(
         QString::unqualified("username".into()),
@@ -230,6 +340,7 @@ mod tests {
   #[test]
   fn string_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike),
       &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::String("apognu".into()))))],
     )
@@ -239,6 +350,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#"glob.match("apognu", null, input.username)"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike),
       &[(
         QString::unqualified("username".into()),
@@ -262,7 +374,22 @@ mod tests {
     assert_eq!(super::to_int(&ConditionValue(V::Integer(10))).unwrap(), Expr::Int(10));
     assert_eq!(super::to_int(&ConditionValue(V::String("10".into()))).unwrap(), Expr::Int(10));
 
+    assert_eq!(super::to_number(&ConditionValue(V::Integer(10))).unwrap(), Expr::Int(10));
+    assert_eq!(super::to_number(&ConditionValue(V::String("10".into()))).unwrap(), Expr::Int(10));
+    assert_eq!(super::to_number(&ConditionValue(V::Float(10.5))).unwrap(), Expr::Float(Float(10.5)));
+    assert_eq!(super::to_number(&ConditionValue(V::String("10.5".into()))).unwrap(), Expr::Float(Float(10.5)));
+    assert_eq!(super::to_number(&ConditionValue(V::String("-0.25".into()))).unwrap(), Expr::Float(Float(-0.25)));
+    assert!(super::to_number(&ConditionValue(V::String("abc".into()))).is_err());
+    assert!(super::to_number(&ConditionValue(V::String("NaN".into()))).is_err());
+    assert!(super::to_number(&ConditionValue(V::Bool(true))).is_err());
+
     assert_eq!(super::to_str(&ConditionValue(V::String("lastring".into()))).unwrap(), Expr::Str(Str::Plain("lastring".into())));
+
+    assert_eq!(super::to_base64(&ConditionValue(V::String("aGVsbG8=".into()))).unwrap(), Expr::Str(Str::Plain("aGVsbG8".into())));
+    assert!(super::to_base64(&ConditionValue(V::String("not base64".into()))).is_err());
+    assert!(super::to_base64(&ConditionValue(V::String("aGVsb".into()))).is_err());
+    assert!(super::to_base64(&ConditionValue(V::String("aGVsbG8===".into()))).is_err());
+    assert!(super::to_base64(&ConditionValue(V::Integer(10))).is_err());
   }
 
   #[test]
@@ -270,11 +397,18 @@ mod tests {
     assert_eq!(super::resolve(&QString::new("aws".into(), "username".into())).unwrap(), Expr::var("input.aws.username"));
     assert_eq!(super::resolve(&QString::new("aws".into(), "tags/region".into())).unwrap(), Expr::var("input.aws.tags.region"));
     assert_eq!(super::resolve(&QString::unqualified("time".into())).unwrap(), Expr::var("input.time"));
+    assert_eq!(super::resolve(&QString::new("s3".into(), "x-amz-acl".into())).unwrap(), Expr::var(r#"input.s3["x-amz-acl"]"#));
+    assert_eq!(
+      super::resolve(&QString::new("aws".into(), "RequestTag/cost-center".into())).unwrap(),
+      Expr::var(r#"input.aws.requesttag["cost-center"]"#)
+    );
+    assert_eq!(super::resolve(&QString::new("app".into(), "in".into())).unwrap(), Expr::var(r#"input.app["in"]"#));
   }
 
   #[test]
   fn string_not_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals),
       &[(QString::unqualified("env".into()), Value::One(ConditionValue(V::String("production".into()))))],
     )
@@ -284,6 +418,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#""production" != input.env"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals),
       &[(
         QString::unqualified("env".into()),
@@ -299,6 +434,7 @@ mod tests {
   #[test]
   fn string_equals_ignore_case() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEqualsIgnoreCase),
       &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::String("APOGNU".into()))))],
     )
@@ -308,6 +444,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#"lower("APOGNU") == lower(input.username)"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEqualsIgnoreCase),
       &[(
         QString::unqualified("username".into()),
@@ -323,6 +460,7 @@ mod tests {
   #[test]
   fn string_not_equals_ignore_case() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEqualsIgnoreCase),
       &[(QString::unqualified("env".into()), Value::One(ConditionValue(V::String("PRODUCTION".into()))))],
     )
@@ -335,6 +473,7 @@ mod tests {
   #[test]
   fn string_not_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotLike),
       &[(QString::unqualified("username".into()), Value::One(ConditionValue(V::String("admin-*".into()))))],
     )
@@ -344,6 +483,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#"not glob.match("admin-*", null, input.username)"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotLike),
       &[(
         QString::unqualified("username".into()),
@@ -359,6 +499,7 @@ mod tests {
   #[test]
   fn numeric_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericEquals),
       &[(QString::unqualified("max_keys".into()), Value::One(ConditionValue(V::Integer(1000))))],
     )
@@ -368,6 +509,7 @@ mod tests {
     assert_eq!(expr[0].repr_to_string().unwrap(), r#"1000 == input.max_keys"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericEquals),
       &[(
         QString::unqualified("max_keys".into()),
@@ -383,18 +525,43 @@ mod tests {
   #[test]
   fn numeric_less_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericLessThan),
       &[(QString::new("s3".into(), "content-length".into()), Value::One(ConditionValue(V::Integer(10485760))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"input.s3.content-length < 10485760"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"input.s3["content-length"] < 10485760"#);
+  }
+
+  #[test]
+  fn numeric_decimal() {
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(NumericLessThan),
+      &[(QString::new("s3".into(), "max-keys".into()), Value::One(ConditionValue(V::String("10.5".into()))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"input.s3["max-keys"] < 10.5"#);
+
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(NumericEquals),
+      &[(QString::unqualified("ratio".into()), Value::Many(vec![ConditionValue(V::Float(0.5)), ConditionValue(V::Integer(1))]))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"[0.5, 1][_] == input.ratio"#);
   }
 
   #[test]
   fn numeric_greater_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericGreaterThan),
       &[(QString::unqualified("age".into()), Value::One(ConditionValue(V::Integer(18))))],
     )
@@ -407,6 +574,7 @@ mod tests {
   #[test]
   fn numeric_less_than_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericLessThanEquals),
       &[(QString::unqualified("max_size".into()), Value::One(ConditionValue(V::Integer(5000))))],
     )
@@ -419,6 +587,7 @@ mod tests {
   #[test]
   fn numeric_greater_than_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericGreaterThanEquals),
       &[(QString::unqualified("min_size".into()), Value::One(ConditionValue(V::Integer(100))))],
     )
@@ -431,6 +600,7 @@ mod tests {
   #[test]
   fn date_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(DateEquals),
       &[(QString::new("aws".into(), "CurrentTime".into()), Value::One(ConditionValue(V::String("2025-01-01T00:00:00Z".into()))))],
     )
@@ -439,13 +609,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"time.parse_rfc3339_ns("2025-01-01T00:00:00Z") == time.parse_rfc3339_ns(input.aws.CurrentTime)"#
+      r#"time.parse_rfc3339_ns("2025-01-01T00:00:00Z") == time.parse_rfc3339_ns(input.aws.currenttime)"#
     );
   }
 
   #[test]
   fn date_greater_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(DateGreaterThan),
       &[(QString::new("aws".into(), "CurrentTime".into()), Value::One(ConditionValue(V::String("2025-01-01T00:00:00Z".into()))))],
     )
@@ -454,13 +625,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"time.parse_rfc3339_ns(input.aws.CurrentTime) > time.parse_rfc3339_ns("2025-01-01T00:00:00Z")"#
+      r#"time.parse_rfc3339_ns(input.aws.currenttime) > time.parse_rfc3339_ns("2025-01-01T00:00:00Z")"#
     );
   }
 
   #[test]
   fn date_less_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(DateLessThan),
       &[(QString::new("aws".into(), "CurrentTime".into()), Value::One(ConditionValue(V::String("2025-12-31T23:59:59Z".into()))))],
     )
@@ -469,22 +641,24 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"time.parse_rfc3339_ns(input.aws.CurrentTime) < time.parse_rfc3339_ns("2025-12-31T23:59:59Z")"#
+      r#"time.parse_rfc3339_ns(input.aws.currenttime) < time.parse_rfc3339_ns("2025-12-31T23:59:59Z")"#
     );
   }
 
   #[test]
   fn ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(IpAddress),
       &[(QString::new("aws".into(), "SourceIp".into()), Value::One(ConditionValue(V::String("192.168.1.0/24".into()))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"net.cidr_contains("192.168.1.0/24", input.aws.SourceIp)"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"net.cidr_contains("192.168.1.0/24", input.aws.sourceip)"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(IpAddress),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -494,21 +668,23 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"net.cidr_contains(["192.168.1.0/24", "10.0.0.0/8"][_], input.aws.SourceIp)"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"net.cidr_contains(["192.168.1.0/24", "10.0.0.0/8"][_], input.aws.sourceip)"#);
   }
 
   #[test]
   fn not_ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NotIpAddress),
       &[(QString::new("aws".into(), "SourceIp".into()), Value::One(ConditionValue(V::String("10.0.0.0/8".into()))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"not net.cidr_contains("10.0.0.0/8", input.aws.SourceIp)"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"not net.cidr_contains("10.0.0.0/8", input.aws.sourceip)"#);
 
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NotIpAddress),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -520,7 +696,91 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in ["10.0.0.0/8", "192.168.0.0/24"] { not net.cidr_contains(item, input.aws.SourceIp) }"#
+      r#"every item in ["10.0.0.0/8", "192.168.0.0/24"] { not net.cidr_contains(item, input.aws.sourceip) }"#
+    );
+  }
+
+  #[test]
+  fn null() {
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(Null),
+      &[(QString::new("aws".into(), "TokenIssueTime".into()), Value::One(ConditionValue(V::String("true".into()))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"data.regoer.prelude.key_null(["aws", "tokenissuetime"]) == true"#);
+
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(Null),
+      &[(QString::new("aws".into(), "RequestTag/env".into()), Value::One(ConditionValue(V::Bool(false))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"data.regoer.prelude.key_null(["aws", "requesttag", "env"]) == false"#);
+  }
+
+  #[test]
+  fn binary_equals() {
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(BinaryEquals),
+      &[(QString::unqualified("signature".into()), Value::One(ConditionValue(V::String("aGVsbG8=".into()))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#""aGVsbG8" == data.regoer.prelude.to_base64(input.signature)"#);
+
+    let expr = build_condition(
+      Version::V2012,
+      &ConditionOperator::new(BinaryEquals),
+      &[(
+        QString::unqualified("signature".into()),
+        Value::Many(vec![ConditionValue(V::String("aGVsbG8=".into())), ConditionValue(V::String("/w==".into()))]),
+      )],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"["aGVsbG8", "/w"][_] == data.regoer.prelude.to_base64(input.signature)"#);
+  }
+
+  #[test]
+  fn if_exists() {
+    let mut operator = ConditionOperator::new(StringEquals);
+    operator.if_exists = true;
+
+    let expr = build_condition(
+      Version::V2012,
+      &operator,
+      &[(QString::new("aws".into(), "userid".into()), Value::One(ConditionValue(V::String("apognu".into()))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"every _ in data.regoer.prelude.if_exists(["aws", "userid"]) { "apognu" == input.aws.userid }"#
+    );
+
+    let mut operator = ConditionOperator::new(Bool);
+    operator.if_exists = true;
+
+    let expr = build_condition(
+      Version::V2012,
+      &operator,
+      &[(QString::new("aws".into(), "MultiFactorAuthPresent".into()), Value::One(ConditionValue(V::Bool(false))))],
+    )
+    .unwrap();
+
+    assert_eq!(expr.len(), 1);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"every _ in data.regoer.prelude.if_exists(["aws", "multifactorauthpresent"]) { input.aws.multifactorauthpresent == false }"#
     );
   }
 
@@ -528,6 +788,7 @@ mod tests {
   fn for_any_value_string_equals() {
     // ForAnyValue: at least one value in the request matches at least one value in the policy
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals).for_any(),
       &[(
         QString::unqualified("tags".into()),
@@ -537,13 +798,17 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"["production", "staging"][_] == to_array(object.get(input, "tags", []))[_]"#);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"["production", "staging"][_] == data.regoer.prelude.to_array(object.get(input, "tags", []))[_]"#
+    );
   }
 
   #[test]
   fn for_any_value_string_not_equals() {
     // ForAnyValue with negation: none of the values in the request match any value in the policy
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals).for_any(),
       &[(
         QString::unqualified("tags".into()),
@@ -555,7 +820,7 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "tags", [])) { every val in ["production", "staging"] { val != item } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { every val in ["production", "staging"] { val != item } }"#
     );
   }
 
@@ -563,6 +828,7 @@ mod tests {
   fn for_all_values_string_equals() {
     // ForAllValues: all values in the request must match at least one value in the policy
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals).for_all(),
       &[(
         QString::unqualified("tags".into()),
@@ -575,7 +841,7 @@ mod tests {
     // This should check that every item in input.tags matches at least one of the policy values
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "tags", [])) { ["production", "staging"][_] == item }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { ["production", "staging"][_] == item }"#
     );
   }
 
@@ -583,6 +849,7 @@ mod tests {
   fn for_all_values_string_not_equals() {
     // ForAllValues with negation: all values in the request must not match any value in the policy
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals).for_all(),
       &[(
         QString::unqualified("tags".into()),
@@ -594,25 +861,27 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "tags", [])) { every val in ["production", "staging"] { val != item } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { every val in ["production", "staging"] { val != item } }"#
     );
   }
 
   #[test]
   fn for_any_value_numeric() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericEquals).for_any(),
       &[(QString::unqualified("ports".into()), Value::Many(vec![ConditionValue(V::Integer(80)), ConditionValue(V::Integer(443))]))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"[80, 443][_] == to_array(object.get(input, "ports", []))[_]"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"[80, 443][_] == data.regoer.prelude.to_array(object.get(input, "ports", []))[_]"#);
   }
 
   #[test]
   fn for_all_values_numeric() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericGreaterThan).for_all(),
       &[(
         QString::unqualified("ports".into()),
@@ -622,12 +891,13 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in to_array(object.get(input, "ports", [])) { item > [1024, 2048][_] }"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in data.regoer.prelude.to_array(object.get(input, "ports", [])) { item > [1024, 2048][_] }"#);
   }
 
   #[test]
   fn for_any_value_string_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike).for_any(),
       &[(
         QString::unqualified("paths".into()),
@@ -637,12 +907,13 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"glob.match(["/home/*", "/tmp/*"][_], null, to_array(object.get(input, "paths", []))[_])"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"glob.match(["/home/*", "/tmp/*"][_], null, data.regoer.prelude.to_array(object.get(input, "paths", []))[_])"#);
   }
 
   #[test]
   fn for_all_values_string_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike).for_all(),
       &[(
         QString::unqualified("paths".into()),
@@ -654,13 +925,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "paths", [])) { glob.match(["/safe/*", "/public/*"][_], null, item) }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "paths", [])) { glob.match(["/safe/*", "/public/*"][_], null, item) }"#
     );
   }
 
   #[test]
   fn for_any_value_ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(IpAddress).for_any(),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -672,13 +944,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"net.cidr_contains(["10.0.0.0/8", "192.168.0.0/16"][_], to_array(object.get(input.aws, "SourceIp", []))[_])"#
+      r#"net.cidr_contains(["10.0.0.0/8", "192.168.0.0/16"][_], data.regoer.prelude.to_array(object.get(input.aws, "sourceip", []))[_])"#
     );
   }
 
   #[test]
   fn for_all_values_ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(IpAddress).for_all(),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -690,13 +963,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input.aws, "SourceIp", [])) { net.cidr_contains(["10.0.0.0/8", "192.168.0.0/16"][_], item) }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input.aws, "sourceip", [])) { net.cidr_contains(["10.0.0.0/8", "192.168.0.0/16"][_], item) }"#
     );
   }
 
   #[test]
   fn for_any_value_numeric_less_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericLessThan).for_any(),
       &[(
         QString::unqualified("values".into()),
@@ -706,12 +980,13 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"to_array(object.get(input, "values", []))[_] < [100, 200][_]"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"data.regoer.prelude.to_array(object.get(input, "values", []))[_] < [100, 200][_]"#);
   }
 
   #[test]
   fn for_all_values_numeric_less_than_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericLessThanEquals).for_all(),
       &[(
         QString::unqualified("limits".into()),
@@ -721,12 +996,13 @@ mod tests {
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in to_array(object.get(input, "limits", [])) { item <= [1000, 5000][_] }"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in data.regoer.prelude.to_array(object.get(input, "limits", [])) { item <= [1000, 5000][_] }"#);
   }
 
   #[test]
   fn for_any_value_date_greater_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(DateGreaterThan).for_any(),
       &[(
         QString::new("aws".into(), "TokenIssueTime".into()),
@@ -738,13 +1014,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"time.parse_rfc3339_ns(to_array(object.get(input.aws, "TokenIssueTime", []))[_]) > time.parse_rfc3339_ns(["2025-01-01T00:00:00Z", "2025-06-01T00:00:00Z"][_])"#
+      r#"time.parse_rfc3339_ns(data.regoer.prelude.to_array(object.get(input.aws, "tokenissuetime", []))[_]) > time.parse_rfc3339_ns(["2025-01-01T00:00:00Z", "2025-06-01T00:00:00Z"][_])"#
     );
   }
 
   #[test]
   fn for_all_values_date_less_than() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(DateLessThan).for_all(),
       &[(
         QString::new("aws".into(), "TokenExpiry".into()),
@@ -756,13 +1033,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input.aws, "TokenExpiry", [])) { time.parse_rfc3339_ns(item) < time.parse_rfc3339_ns(["2026-12-31T23:59:59Z", "2027-12-31T23:59:59Z"][_]) }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input.aws, "tokenexpiry", [])) { time.parse_rfc3339_ns(item) < time.parse_rfc3339_ns(["2026-12-31T23:59:59Z", "2027-12-31T23:59:59Z"][_]) }"#
     );
   }
 
   #[test]
   fn for_any_value_string_not_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotLike).for_any(),
       &[(
         QString::unqualified("paths".into()),
@@ -774,13 +1052,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "paths", [])) { every val in ["/admin/*", "/root/*"] { not glob.match(val, null, item) } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "paths", [])) { every val in ["/admin/*", "/root/*"] { not glob.match(val, null, item) } }"#
     );
   }
 
   #[test]
   fn for_all_values_numeric_not_equals() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericNotEquals).for_all(),
       &[(QString::unqualified("ports".into()), Value::Many(vec![ConditionValue(V::Integer(22)), ConditionValue(V::Integer(23))]))],
     )
@@ -789,7 +1068,7 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "ports", [])) { every val in [22, 23] { val != item } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "ports", [])) { every val in [22, 23] { val != item } }"#
     );
   }
 
@@ -799,6 +1078,7 @@ mod tests {
   #[test]
   fn for_any_value_string_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals).for_any(),
       &[(QString::unqualified("tags".into()), Value::One(ConditionValue(V::String("production".into()))))],
     )
@@ -806,12 +1086,16 @@ mod tests {
 
     assert_eq!(expr.len(), 1);
     // At least one context value must equal the single policy value
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"["production"][_] == to_array(object.get(input, "tags", []))[_]"#);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"["production"][_] == data.regoer.prelude.to_array(object.get(input, "tags", []))[_]"#
+    );
   }
 
   #[test]
   fn for_all_values_string_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringEquals).for_all(),
       &[(QString::unqualified("tags".into()), Value::One(ConditionValue(V::String("production".into()))))],
     )
@@ -819,12 +1103,13 @@ mod tests {
 
     assert_eq!(expr.len(), 1);
     // Every context value must equal the single policy value
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in to_array(object.get(input, "tags", [])) { ["production"][_] == item }"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { ["production"][_] == item }"#);
   }
 
   #[test]
   fn for_any_value_string_not_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals).for_any(),
       &[(QString::unqualified("tags".into()), Value::One(ConditionValue(V::String("production".into()))))],
     )
@@ -834,13 +1119,14 @@ mod tests {
     // No context value must equal the single policy value
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "tags", [])) { every val in ["production"] { val != item } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { every val in ["production"] { val != item } }"#
     );
   }
 
   #[test]
   fn for_all_values_string_not_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotEquals).for_all(),
       &[(QString::unqualified("tags".into()), Value::One(ConditionValue(V::String("production".into()))))],
     )
@@ -849,49 +1135,59 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "tags", [])) { every val in ["production"] { val != item } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "tags", [])) { every val in ["production"] { val != item } }"#
     );
   }
 
   #[test]
   fn for_any_value_numeric_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericEquals).for_any(),
       &[(QString::unqualified("ports".into()), Value::One(ConditionValue(V::Integer(443))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"[443][_] == to_array(object.get(input, "ports", []))[_]"#);
+    assert_eq!(expr[0].repr_to_string().unwrap(), r#"[443][_] == data.regoer.prelude.to_array(object.get(input, "ports", []))[_]"#);
   }
 
   #[test]
   fn for_all_values_numeric_equals_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NumericEquals).for_all(),
       &[(QString::unqualified("ports".into()), Value::One(ConditionValue(V::Integer(443))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"every item in to_array(object.get(input, "ports", [])) { [443][_] == item }"#);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "ports", [])) { [443][_] == item }"#
+    );
   }
 
   #[test]
   fn for_any_value_string_like_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike).for_any(),
       &[(QString::unqualified("paths".into()), Value::One(ConditionValue(V::String("/home/*".into()))))],
     )
     .unwrap();
 
     assert_eq!(expr.len(), 1);
-    assert_eq!(expr[0].repr_to_string().unwrap(), r#"glob.match(["/home/*"][_], null, to_array(object.get(input, "paths", []))[_])"#);
+    assert_eq!(
+      expr[0].repr_to_string().unwrap(),
+      r#"glob.match(["/home/*"][_], null, data.regoer.prelude.to_array(object.get(input, "paths", []))[_])"#
+    );
   }
 
   #[test]
   fn for_all_values_string_like_single_policy_value() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringLike).for_all(),
       &[(QString::unqualified("paths".into()), Value::One(ConditionValue(V::String("/safe/*".into()))))],
     )
@@ -900,13 +1196,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "paths", [])) { glob.match(["/safe/*"][_], null, item) }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "paths", [])) { glob.match(["/safe/*"][_], null, item) }"#
     );
   }
 
   #[test]
   fn for_all_values_string_not_like() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(StringNotLike).for_all(),
       &[(
         QString::unqualified("paths".into()),
@@ -918,13 +1215,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input, "paths", [])) { every val in ["/admin/*", "/root/*"] { not glob.match(val, null, item) } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input, "paths", [])) { every val in ["/admin/*", "/root/*"] { not glob.match(val, null, item) } }"#
     );
   }
 
   #[test]
   fn for_any_value_not_ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NotIpAddress).for_any(),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -936,13 +1234,14 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input.aws, "SourceIp", [])) { every val in ["10.0.0.0/8", "192.168.0.0/16"] { not net.cidr_contains(val, item) } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input.aws, "sourceip", [])) { every val in ["10.0.0.0/8", "192.168.0.0/16"] { not net.cidr_contains(val, item) } }"#
     );
   }
 
   #[test]
   fn for_all_values_not_ip_address() {
     let expr = build_condition(
+      Version::V2012,
       &ConditionOperator::new(NotIpAddress).for_all(),
       &[(
         QString::new("aws".into(), "SourceIp".into()),
@@ -954,7 +1253,7 @@ mod tests {
     assert_eq!(expr.len(), 1);
     assert_eq!(
       expr[0].repr_to_string().unwrap(),
-      r#"every item in to_array(object.get(input.aws, "SourceIp", [])) { every val in ["10.0.0.0/8", "192.168.0.0/16"] { not net.cidr_contains(val, item) } }"#
+      r#"every item in data.regoer.prelude.to_array(object.get(input.aws, "sourceip", [])) { every val in ["10.0.0.0/8", "192.168.0.0/16"] { not net.cidr_contains(val, item) } }"#
     );
   }
 }
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{parser::PolicyType, values::Effect};
+
+/// Outcome of the evaluation of a request against a policy set.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+  /// At least one statement allowed the request, and none denied it.
+  Allow,
+  /// At least one statement explicitly denied the request.
+  ExplicitDeny,
+  /// No statement matched the request.
+  ImplicitDeny,
+}
+
+/// Reference to a statement that matched a request.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MatchedStatement {
+  /// Index of the policy, in the order policies were added to [`Regoer`](crate::Regoer).
+  pub policy: usize,
+  /// Index of the statement within its policy.
+  pub statement: usize,
+  /// `Sid` of the statement, if any.
+  pub sid: Option<String>,
+}
+
+/// Decision rendered by an [`Evaluator`](crate::Evaluator) for a request.
+///
+/// Along with the outcome, it lists the statements that caused it: the
+/// statements that denied the request on an explicit deny, or the statements
+/// that allowed it. It is empty on an implicit deny.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+  pub outcome: Outcome,
+  pub statements: Vec<MatchedStatement>,
+}
+
+impl Decision {
+  /// Whether the request should be accepted.
+  pub fn is_allowed(&self) -> bool {
+    self.outcome == Outcome::Allow
+  }
+}
+
+/// Detailed evaluation of a request, as returned by [`Evaluator::explain()`](crate::Evaluator::explain()).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Explanation {
+  pub decision: Decision,
+  /// Policies, in the order they were added to [`Regoer`](crate::Regoer).
+  pub policies: Vec<PolicyExplanation>,
+}
+
+/// Evaluation of the statements of a policy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolicyExplanation {
+  /// Index of the policy, in the order policies were added to [`Regoer`](crate::Regoer).
+  pub policy: usize,
+  #[serde(rename = "type")]
+  pub kind: PolicyType,
+  /// Whether the policy takes part in the decision, service control policies
+  /// attached outside of the organization path of the principal not being
+  /// applicable.
+  pub applies: bool,
+  pub statements: Vec<StatementExplanation>,
+}
+
+/// Evaluation of each element of a statement.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatementExplanation {
+  /// Index of the statement within its policy.
+  pub statement: usize,
+  /// `Sid` of the statement, if any.
+  pub sid: Option<String>,
+  pub effect: Effect,
+  /// Whether the `Principal` or `NotPrincipal` element matched the request.
+  pub principal: bool,
+  /// Whether the `Action` or `NotAction` element matched the request.
+  pub action: bool,
+  /// Whether the `Resource` or `NotResource` element matched the request.
+  pub resource: bool,
+  /// Conditions of the statement, one per operator and key.
+  pub conditions: Vec<ConditionExplanation>,
+}
+
+impl StatementExplanation {
+  /// Whether the statement matched the request.
+  pub fn matched(&self) -> bool {
+    self.principal && self.action && self.resource && self.conditions.iter().all(|condition| condition.passed)
+  }
+}
+
+/// Evaluation of a condition key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConditionExplanation {
+  /// Condition operator, such as `StringEquals` or `ForAnyValue:StringLikeIfExists`.
+  pub operator: String,
+  /// Condition key, as written in the policy.
+  pub key: String,
+  /// Values the key is compared against.
+  pub values: Vec<serde_json::Value>,
+  pub passed: bool,
+}
@@ -1,6 +1,6 @@
 use crate::{
   Error,
-  expression::{Expr, Repr},
+  expression::{Expr, Header, Repr},
 };
 
 macro_rules! emit {
@@ -19,6 +19,12 @@ impl Emit for Expr {
   }
 }
 
+impl Emit for Header {
+  fn emit(&self, w: &mut String) -> Result<(), Error> {
+    self.repr(w)
+  }
+}
+
 impl Emit for &[Expr] {
   fn emit(&self, w: &mut String) -> Result<(), Error> {
     self.repr(w)
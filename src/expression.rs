@@ -1,16 +1,19 @@
 use std::fmt::{self, Write};
 
 use crate::{
-  conditions::{Conditions, build_condition},
+  conditions::{Conditions, build_condition, operator_name},
   emit::Emit,
+  functions::Func,
   interpolation::substitute_variables,
+  location::ErrorLocation,
   parser::Error,
-  values::Effect,
+  source::{condition_pointer, pointer},
+  values::{Effect, Value, Version},
 };
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Expr {
-  Statement(Effect, Vec<Expr>, Conditions),
+  Statement(Header, Box<Scopes>, Conditions),
 
   Call(Call),
   Var(Var),
@@ -19,6 +22,7 @@ pub enum Expr {
   Bool(bool),
   Str(Str),
   Int(i64),
+  Float(Float),
 
   Neg(Box<Expr>),
   Eq(Box<Expr>, Box<Expr>),
@@ -40,6 +44,22 @@ impl Expr {
     Expr::Var(Var(var.as_ref().to_string()))
   }
 
+  pub fn path<S: AsRef<str>>(root: &str, segments: &[S]) -> Expr {
+    let mut path = root.to_string();
+
+    for segment in segments {
+      let segment = segment.as_ref();
+
+      match is_identifier(segment) {
+        true => write!(path, ".{segment}"),
+        false => write!(path, "[{}]", quote(segment)),
+      }
+      .expect("writing to a String cannot fail");
+    }
+
+    Expr::Var(Var(path))
+  }
+
   pub fn id(expr: Expr) -> Expr {
     expr
   }
@@ -64,6 +84,13 @@ impl Expr {
     Ok(Expr::Int(i))
   }
 
+  pub fn float(f: f64) -> Result<Expr, Error> {
+    match f.is_finite() {
+      true => Ok(Expr::Float(Float(f))),
+      false => Err(Error::InvalidType("number", f.to_string())),
+    }
+  }
+
   pub fn every<F>(list: Vec<Expr>, f: F) -> Result<Expr, Error>
   where
     F: Fn(Expr) -> Result<Expr, Error>,
@@ -78,6 +105,65 @@ impl Expr {
   pub fn boxed(self) -> Box<Expr> {
     Box::new(self)
   }
+
+  /// Escapes glob metacharacters in string literals, apart from the `*` and `?` wildcards.
+  ///
+  /// Values substituted for policy variables are escaped entirely, wildcards
+  /// included, so that they are matched literally.
+  pub fn glob_pattern(self) -> Expr {
+    match self {
+      Expr::Str(Str::Plain(s)) => Expr::Str(Str::Plain(escape_glob(&s))),
+      Expr::Str(Str::Template(t, vars)) => Expr::Str(Str::Template(escape_glob(&t), vars.into_iter().map(Func::glob_escape).collect())),
+      Expr::List(list) => Expr::List(list.into_iter().map(Expr::glob_pattern).collect()),
+      Expr::AnyIn(e) => Expr::AnyIn(e.glob_pattern().boxed()),
+      e => e,
+    }
+  }
+}
+
+fn escape_glob(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    if matches!(c, '\\' | '[' | ']' | '{' | '}') {
+      out.push('\\');
+    }
+
+    out.push(c);
+  }
+
+  out
+}
+
+const KEYWORDS: &[&str] = &[
+  "as", "contains", "default", "else", "every", "false", "if", "import", "in", "not", "null", "package", "some", "true", "with",
+];
+
+fn is_identifier(s: &str) -> bool {
+  let mut chars = s.chars();
+
+  matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && !KEYWORDS.contains(&s)
+}
+
+pub(crate) fn quote(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+
+  out.push('"');
+
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str(r#"\""#),
+      '\\' => out.push_str(r"\\"),
+      '\n' => out.push_str(r"\n"),
+      '\r' => out.push_str(r"\r"),
+      '\t' => out.push_str(r"\t"),
+      c if c.is_control() => out.push_str(&format!(r"\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+
+  out.push('"');
+  out
 }
 
 pub trait Repr: fmt::Debug + Eq + PartialEq {
@@ -94,26 +180,87 @@ pub trait Repr: fmt::Debug + Eq + PartialEq {
 impl Repr for Expr {
   fn repr(&self, buf: &mut String) -> Result<(), Error> {
     match self {
-      #[allow(unused_must_use)]
-      Expr::Statement(effect, exprs, conditions) => {
-        match effect {
-          Effect::Allow => buf.push_str("permit if {\n"),
-          Effect::Deny => buf.push_str("deny if {\n"),
-        }
+      Expr::Statement(header, scopes, conditions) => {
+        let name = format!("statement_{}", header.index);
+        let mut rules = vec![];
 
-        for expr in exprs {
-          emit!(buf, "  ", expr, '\n');
+        let exprs = [("principal", &scopes.principal), ("action", &scopes.action), ("resource", &scopes.resource)];
+
+        for ((scope, expr), element) in exprs.into_iter().zip(scopes.elements) {
+          let rule = format!("{name}_{scope}");
+
+          match element {
+            Some(element) => annotate(buf, header, &pointer(&header.pointer, element))?,
+            None => annotate(buf, header, &header.pointer)?,
+          }
+
+          rule_repr(buf, &rule, expr.as_ref())?;
+          rules.push(rule);
         }
 
-        if !conditions.is_empty() {
-          for (operator, condition) in conditions {
-            for cond in build_condition(operator, condition)? {
-              emit!(buf, "  ", cond, '\n');
-            }
+        let mut details = vec![];
+
+        for (operator, condition) in conditions {
+          for pair @ (key, values) in condition {
+            let rule = format!("{name}_condition_{}", details.len());
+            let values = match values {
+              Value::One(one) => serde_json::to_string(&[one])?,
+              Value::Many(list) => serde_json::to_string(list)?,
+            };
+
+            let source = condition_pointer(&header.pointer, operator, key);
+
+            let location = ErrorLocation::statement(header.index, header.sid.as_deref(), source.clone()).condition(operator_name(operator), key.to_string());
+            let cond = build_condition(header.version, operator, std::slice::from_ref(pair)).map_err(|err| err.at(location))?;
+
+            annotate(buf, header, &source)?;
+            rule_repr(buf, &rule, cond.first())?;
+            rules.push(rule.clone());
+
+            details.push(format!(
+              r#"{{"operator": {}, "key": {}, "values": {values}, "passed": {rule}}}"#,
+              quote(&operator_name(operator)),
+              quote(&key.to_string())
+            ));
           }
         }
 
+        annotate(buf, header, &header.pointer)?;
+        emit!(buf, "matched contains ", header, " if {\n");
+
+        for rule in &rules {
+          emit!(buf, "  ", rule.as_str(), '\n');
+        }
+
         buf.push_str("}\n");
+
+        // Statements naming the principal of the request exactly, which exempts it from boundaries in resource-based policies.
+        if let Some(named) = &scopes.named {
+          let rule = format!("{name}_named");
+
+          annotate(buf, header, &pointer(&header.pointer, "Principal"))?;
+          rule_repr(buf, &rule, Some(named))?;
+
+          annotate(buf, header, &header.pointer)?;
+          emit!(buf, "named contains ", header, " if {\n");
+
+          for rule in rules.iter().chain([&rule]) {
+            emit!(buf, "  ", rule.as_str(), '\n');
+          }
+
+          buf.push_str("}\n");
+        }
+
+        // Scopes and conditions are reported whether they hold or not, for `Evaluator::explain()`.
+        emit!(buf, "explanation contains object.union(", header, ", {");
+        writeln!(
+          buf,
+          r#""principal": {}, "action": {}, "resource": {}, "conditions": [{}]}})"#,
+          rules[0],
+          rules[1],
+          rules[2],
+          details.join(", ")
+        )?;
       }
 
       Expr::Call(e) => e.repr(buf)?,
@@ -125,6 +272,7 @@ impl Repr for Expr {
       Expr::Bool(e) => write!(buf, "{e}")?,
       Expr::Str(e) => e.repr(buf)?,
       Expr::Int(e) => write!(buf, "{e}")?,
+      Expr::Float(e) => write!(buf, "{}", e.0)?,
       Expr::Neg(e) => {
         emit!(buf, "not ", e);
       }
@@ -155,6 +303,75 @@ impl Repr for Expr {
   }
 }
 
+/// Emits a comment tracing the following rule back to the policy document, read by [`SourceMap`](crate::SourceMap).
+fn annotate(buf: &mut String, header: &Header, pointer: &str) -> Result<(), Error> {
+  write!(buf, "# {pointer} (statement {}", header.index)?;
+
+  if let Some(sid) = &header.sid {
+    write!(buf, ", {}", quote(sid))?;
+  }
+
+  writeln!(buf, ")")?;
+
+  Ok(())
+}
+
+/// Emits a boolean rule, `true` if `expr` holds, or unconditionally if there is none.
+fn rule_repr(buf: &mut String, rule: &str, expr: Option<&Expr>) -> Result<(), Error> {
+  match expr {
+    Some(expr) => {
+      writeln!(buf, "default {rule} := false")?;
+      emit!(buf, rule, " if {\n  ", expr, "\n}\n");
+    }
+    None => writeln!(buf, "{rule} := true")?,
+  }
+
+  Ok(())
+}
+
+/// Scopes of a statement, `None` when matching any request.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Scopes {
+  pub principal: Option<Expr>,
+  pub action: Option<Expr>,
+  pub resource: Option<Expr>,
+  /// Whether the statement names the principal of the request, only set for `Principal` elements without wildcards.
+  pub named: Option<Expr>,
+  /// Elements the scopes were read from, such as `NotAction`, if present in the document.
+  pub elements: [Option<&'static str>; 3],
+}
+
+/// Identification of a statement within its policy, reported when it matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Header {
+  pub version: Version,
+  pub index: usize,
+  /// JSON pointer to the statement in the policy document.
+  pub pointer: String,
+  pub sid: Option<String>,
+  pub effect: Effect,
+}
+
+impl Repr for Header {
+  fn repr(&self, buf: &mut String) -> Result<(), Error> {
+    let effect = match self.effect {
+      Effect::Allow => "Allow",
+      Effect::Deny => "Deny",
+    };
+
+    write!(buf, r#"{{"policy": policy, "statement": {}, "sid": "#, self.index)?;
+
+    match &self.sid {
+      Some(sid) => Str::literal(sid).repr(buf)?,
+      None => buf.push_str("null"),
+    }
+
+    write!(buf, r#", "effect": "{effect}"}}"#)?;
+
+    Ok(())
+  }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Str {
   Plain(String),
@@ -180,7 +397,7 @@ impl From<String> for Str {
 impl Repr for Str {
   fn repr(&self, buf: &mut String) -> Result<(), Error> {
     match self {
-      Str::Plain(s) => write!(buf, r#""{s}""#)?,
+      Str::Plain(s) => buf.push_str(&quote(s)),
       Str::Template(t, vars) => {
         emit!(buf, "sprintf(", Expr::Str(Str::literal(t)), ", ", vars.as_slice(), ")");
       }
@@ -190,6 +407,17 @@ impl Repr for Str {
   }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+  fn eq(&self, other: &Self) -> bool {
+    self.0.to_bits() == other.0.to_bits()
+  }
+}
+
+impl Eq for Float {}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Var(pub String);
 
@@ -237,3 +465,43 @@ impl<T: Repr> Repr for &[T] {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{Expr, Repr, Str};
+
+  #[test]
+  fn escape_strings() {
+    assert_eq!(Expr::Str(Str::literal("plain")).repr_to_string().unwrap(), r#""plain""#);
+    assert_eq!(Expr::Str(Str::literal(r#"a"b\c"#)).repr_to_string().unwrap(), r#""a\"b\\c""#);
+    assert_eq!(Expr::Str(Str::literal("a\nb\tc\r")).repr_to_string().unwrap(), r#""a\nb\tc\r""#);
+    assert_eq!(Expr::Str(Str::literal("\u{0}\u{1b}\u{7f}")).repr_to_string().unwrap(), r#""\u0000\u001b\u007f""#);
+    assert_eq!(Expr::Str(Str::literal("é∑")).repr_to_string().unwrap(), r#""é∑""#);
+  }
+
+  #[test]
+  fn escape_templates() {
+    let expr = Expr::Str(Str::tmpl(r#"a"%s\"#, vec![Expr::var("input.username")]));
+
+    assert_eq!(expr.repr_to_string().unwrap(), r#"sprintf("a\"%s\\", [input.username])"#);
+  }
+
+  #[test]
+  fn escape_glob_patterns() {
+    assert_eq!(Expr::Str(Str::literal("a*b?")).glob_pattern(), Expr::Str(Str::literal("a*b?")));
+    assert_eq!(Expr::Str(Str::literal(r"${a}[b]\")).glob_pattern(), Expr::Str(Str::literal(r"$\{a\}\[b\]\\")));
+    assert_eq!(
+      Expr::AnyIn(Expr::list(vec![Expr::Str(Str::literal("{x}"))]).boxed()).glob_pattern(),
+      Expr::AnyIn(Expr::list(vec![Expr::Str(Str::literal(r"\{x\}"))]).boxed())
+    );
+    assert_eq!(
+      Expr::Str(Str::tmpl("[%s]", vec![Expr::var("input.username")])).glob_pattern().repr_to_string().unwrap(),
+      r#"sprintf("\\[%s\\]", [data.regoer.prelude.glob_escape(input.username)])"#
+    );
+  }
+
+  #[test]
+  fn escape_paths() {
+    assert_eq!(Expr::path("input", &["aws", r#"a"b"#]), Expr::var(r#"input.aws["a\"b"]"#));
+  }
+}
@@ -1,10 +1,14 @@
-use crate::expression::Expr;
+use crate::expression::{Expr, Str};
 
 pub struct Func;
 
 impl Func {
   pub fn glob(lhs: Expr, rhs: Expr) -> Expr {
-    Expr::call("glob.match", vec![lhs, Expr::var("null"), rhs])
+    Expr::call("glob.match", vec![lhs.glob_pattern(), Expr::var("null"), rhs])
+  }
+
+  pub fn glob_escape(value: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.glob_escape", vec![value])
   }
 
   pub fn lower(lhs: Expr) -> Expr {
@@ -19,21 +23,48 @@ impl Func {
     Expr::call("time.parse_rfc3339_ns", vec![dt])
   }
 
+  pub fn to_base64(expr: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.to_base64", vec![expr])
+  }
+
   pub fn arn_like(lhs: Expr, rhs: Expr) -> Expr {
-    Expr::call("arn_like", vec![lhs, rhs])
+    Expr::call("data.regoer.prelude.arn_like", vec![lhs.glob_pattern(), rhs])
   }
 
-  pub fn to_array(expr: Expr) -> Expr {
-    let path = match &expr {
-      Expr::Var(v) => &v.0,
-      _ => return expr,
-    };
+  pub fn resource_match(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.resource_match", vec![lhs.glob_pattern(), rhs])
+  }
+
+  pub fn principal_match(kind: Expr, id: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.principal_match", vec![kind, id])
+  }
+
+  pub fn principal_in(principals: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.principal_in", vec![principals])
+  }
+
+  pub fn principal_named(principals: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.principal_named", vec![principals])
+  }
+
+  pub fn principal_excluded(principals: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.principal_excluded", vec![principals])
+  }
+
+  pub fn if_exists(path: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.if_exists", vec![path])
+  }
+
+  pub fn key_null(path: Expr) -> Expr {
+    Expr::call("data.regoer.prelude.key_null", vec![path])
+  }
 
-    let var = match path.rsplit_once('.') {
-      Some((object, key)) => Expr::call("object.get", vec![Expr::var(object), Expr::var(format!(r#""{key}""#)), Expr::List(vec![])]),
-      None => expr,
+  pub fn to_array<S: AsRef<str>>(root: &str, path: &[S]) -> Expr {
+    let var = match path.split_last() {
+      Some((key, object)) => Expr::call("object.get", vec![Expr::path(root, object), Expr::Str(Str::literal(key)), Expr::List(vec![])]),
+      None => Expr::var(root),
     };
 
-    Expr::call("to_array", vec![var])
+    Expr::call("data.regoer.prelude.to_array", vec![var])
   }
 }
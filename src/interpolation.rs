@@ -16,7 +16,7 @@ pub enum SubstitutionError {
   EmptyExpression,
 }
 
-const INPUT_PREFIX: &str = "input.";
+const INPUT: &str = "input";
 
 pub fn substitute_variables(template: &str) -> Result<Str, Error> {
   if !template.contains("${") {
@@ -37,7 +37,7 @@ pub fn substitute_variables(template: &str) -> Result<Str, Error> {
         let end = search_start + relative_end;
         let var_expr = &template[search_start..end];
 
-        result.push_str(&template[last_end..start]);
+        result.push_str(&escape_format(&template[last_end..start]));
 
         if let Some(special_value) = handle_special_variable(var_expr) {
           result.push_str(special_value);
@@ -50,26 +50,12 @@ pub fn substitute_variables(template: &str) -> Result<Str, Error> {
 
         validate_variable_expr(var_part)?;
 
-        let mut variable = String::with_capacity(INPUT_PREFIX.len() + var_part.len());
-
-        variable.push_str(INPUT_PREFIX);
-
-        for c in var_part.chars() {
-          variable.push(if c == ':' || c == '/' { '.' } else { c });
-        }
+        let path: Vec<String> = var_part.split([':', '/']).map(str::to_lowercase).collect();
 
         let variable: Expr = if let Some(default) = default_value {
-          let (object, path) = {
-            let mut parts = variable.split('.');
-            let object = parts.next().unwrap().to_string();
-            let path = parts.map_expr(Expr::str)?;
-
-            (object, path)
-          };
-
-          Expr::call("object.get", vec![Expr::var(object), Expr::list(path), Expr::str(default)?])
+          Expr::call("object.get", vec![Expr::var(INPUT), Expr::list(path.map_expr(Expr::str)?), Expr::str(default)?])
         } else {
-          Expr::var(variable)
+          Expr::path(INPUT, &path)
         };
 
         result.push_str("%s");
@@ -78,16 +64,24 @@ pub fn substitute_variables(template: &str) -> Result<Str, Error> {
         last_end = end + 1;
         pos = end + 1;
       } else {
-        result.push_str(&template[last_end..]);
+        result.push_str(&escape_format(&template[last_end..]));
         break;
       }
     } else {
-      result.push_str(&template[last_end..]);
+      result.push_str(&escape_format(&template[last_end..]));
       break;
     }
   }
 
-  if !vars.is_empty() { Ok(Str::tmpl(result, vars)) } else { Ok(Str::Plain(result)) }
+  if !vars.is_empty() {
+    Ok(Str::tmpl(result, vars))
+  } else {
+    Ok(Str::Plain(result.replace("%%", "%")))
+  }
+}
+
+fn escape_format(literal: &str) -> String {
+  literal.replace('%', "%%")
 }
 
 fn handle_special_variable(expr: &str) -> Option<&'static str> {
@@ -248,7 +242,21 @@ mod tests {
   fn valid_characters() {
     let result = substitute_variables("${aws:user-name_v1.0}").unwrap();
 
-    assert_eq!(result, Str::tmpl("%s", vec![Expr::var("input.aws.user-name_v1.0")]));
+    assert_eq!(result, Str::tmpl("%s", vec![Expr::var(r#"input.aws["user-name_v1.0"]"#)]));
+  }
+
+  #[test]
+  fn special_characters() {
+    let result = substitute_variables("${aws:RequestTag/cost-center}").unwrap();
+
+    assert_eq!(result, Str::tmpl("%s", vec![Expr::var(r#"input.aws.requesttag["cost-center"]"#)]));
+
+    let result = substitute_variables("${s3:x-amz-acl}/${aws:PrincipalTag/team.name}").unwrap();
+
+    assert_eq!(
+      result,
+      Str::tmpl("%s/%s", vec![Expr::var(r#"input.s3["x-amz-acl"]"#), Expr::var(r#"input.aws.principaltag["team.name"]"#)])
+    );
   }
 
   #[test]
@@ -386,6 +394,17 @@ mod tests {
     assert_eq!(result, Str::Plain("$".to_string()));
   }
 
+  #[test]
+  fn percent_signs() {
+    let result = substitute_variables("100% ${aws:username} %s").unwrap();
+
+    assert_eq!(result, Str::tmpl("100%% %s %%s", vec![Expr::var("input.aws.username")]));
+
+    let result = substitute_variables("100% ${$} %s").unwrap();
+
+    assert_eq!(result, Str::Plain("100% $ %s".to_string()));
+  }
+
   #[test]
   fn complex_exception() {
     let result = substitute_variables("arn:aws:s3:::${bucket}/${*}").unwrap();
@@ -1,22 +1,41 @@
 #![doc = include_str!("../README.md")]
 
 mod conditions;
+mod decision;
 #[macro_use]
 mod emit;
 mod expression;
 mod extensions;
 mod functions;
 mod interpolation;
+mod lint;
+mod location;
 mod parser;
+mod scheme;
+mod source;
 mod statement;
+mod sts;
+mod validation;
 mod values;
 
-use std::{fmt, io, sync::Arc};
+use std::{collections::BTreeMap, fmt, io, sync::Arc};
 
 use regorus::{CompiledPolicy, Engine};
 use serde::Serialize;
+use serde_json::json;
 
-pub use crate::parser::{Error, Policy};
+use crate::parser::{MAIN, PRELUDE};
+pub use crate::{
+  decision::{ConditionExplanation, Decision, Explanation, MatchedStatement, Outcome, PolicyExplanation, StatementExplanation},
+  lint::{Finding, FindingCode, FindingSeverity},
+  location::ErrorLocation,
+  parser::{Error, Policy, PolicyType},
+  scheme::ResourceScheme,
+  source::{SourceLocation, SourceMap},
+  sts::{AssumeRole, Session},
+  validation::{Diagnostic, Severity},
+  values::Effect,
+};
 
 /// AWS IAM policy parser
 ///
@@ -60,6 +79,9 @@ pub use crate::parser::{Error, Policy};
 pub struct Regoer {
   engine: Engine,
   policies: Vec<Policy>,
+  organization: BTreeMap<String, Option<String>>,
+  schemes: BTreeMap<String, ResourceScheme>,
+  exempt_account_principals: bool,
 }
 
 /// Collection of [`Policy`] to be evaluated.
@@ -67,7 +89,9 @@ pub struct Regoer {
 /// Can be build by calling [`Regoer::compile()`].
 pub struct Evaluator {
   policy: CompiledPolicy,
+  explanation: CompiledPolicy,
   policies: Vec<Policy>,
+  source_maps: Vec<SourceMap>,
 }
 
 impl Default for Regoer {
@@ -75,23 +99,161 @@ impl Default for Regoer {
     Self {
       engine: Engine::new(),
       policies: vec![],
+      organization: BTreeMap::new(),
+      schemes: BTreeMap::from([("arn".into(), ResourceScheme::arn())]),
+      exempt_account_principals: false,
     }
   }
 }
 
 impl Regoer {
-  /// Appends a policy to the evaluator
+  /// Appends an identity-based policy to the evaluator
   ///
-  /// An arbitrary number of policies can be added to [`Regoer`], they will all
-  /// be concatenated and used to drive decisions.
+  /// An arbitrary number of policies can be added to [`Regoer`], each of them
+  /// is emitted into its own Rego package and their results are combined to
+  /// drive decisions.
   ///
   /// This method accepts any item implementing [`io::Read`].
   pub fn add_policy<R>(&mut self, input: R) -> Result<(), Error>
   where
     R: io::Read,
   {
-    let policy = parser::parse_iam_policy(input)?;
+    self.add_policy_with_type(PolicyType::Identity, input)
+  }
+
+  /// Appends a policy of the given type to the evaluator
+  ///
+  /// Policies are combined following the AWS evaluation logic:
+  ///
+  ///  * an explicit `Deny` in any policy denies the request ;
+  ///  * if any service control policy was added, one of them must allow the request ;
+  ///  * a resource-based policy allowing the request grants it on its own ;
+  ///  * otherwise, an identity-based policy must allow the request, as well as a
+  ///    permission boundary and a session policy, if any of those were added.
+  pub fn add_policy_with_type<R>(&mut self, kind: PolicyType, input: R) -> Result<(), Error>
+  where
+    R: io::Read,
+  {
+    let mut policy = parser::parse_iam_policy(input)?;
 
+    policy.id = self.policies.len();
+    policy.kind = kind;
+    self.policies.push(policy);
+
+    Ok(())
+  }
+
+  /// Validates a policy document, without adding it to the evaluator
+  ///
+  /// Unlike [`Regoer::add_policy()`], which stops on the first error, every
+  /// statement and condition is checked, and the complete list of errors and
+  /// warnings is returned, along with their location in the document. The
+  /// document is valid if no [`Severity::Error`] diagnostic is returned.
+  pub fn validate<R>(input: R) -> Vec<Diagnostic>
+  where
+    R: io::Read,
+  {
+    match parser::read_document(input) {
+      Ok(document) => validation::validate(&document),
+      Err(err) => vec![err.into()],
+    }
+  }
+
+  /// Lints a policy document, without adding it to the evaluator
+  ///
+  /// Beyond the errors preventing a policy from being compiled, statements
+  /// are checked for overly broad access, such as `Allow` statements on every
+  /// action and resource, for statements made redundant or shadowed by
+  /// another one, for condition keys which do not exist for the services of
+  /// the statement actions, for malformed ARNs and for duplicated `Sid`s.
+  ///
+  /// Findings are reported in document order, with their location. The
+  /// document must be valid, as with [`Regoer::add_policy()`].
+  ///
+  /// Like [`Regoer::validate()`], linting works on the document rather than
+  /// on an added [`Policy`]: findings are located by line and column in its
+  /// source, which policies do not keep, their statements being transpiled
+  /// to Rego as soon as they are added.
+  pub fn lint<R>(input: R) -> Result<Vec<Finding>, Error>
+  where
+    R: io::Read,
+  {
+    lint::lint_document(&parser::read_document(input)?)
+  }
+
+  /// Adds a node to the organization tree
+  ///
+  /// Nodes mirror AWS Organizations: a root without a parent, organizational
+  /// units, and accounts, identified by their account ID, as leaves. Parents
+  /// must be added before their children.
+  ///
+  /// Service control policies can then be attached to any node with
+  /// [`Regoer::add_service_control_policy()`](Self::add_service_control_policy()).
+  pub fn add_organization_node(&mut self, id: impl AsRef<str>, parent: Option<&str>) -> Result<(), Error> {
+    let id = id.as_ref();
+
+    if self.organization.contains_key(id) {
+      return Err(Error::DuplicateOrganizationNode(id.to_string()));
+    }
+
+    if let Some(parent) = parent
+      && !self.organization.contains_key(parent)
+    {
+      return Err(Error::UnknownOrganizationNode(parent.to_string()));
+    }
+
+    self.organization.insert(id.to_string(), parent.map(ToString::to_string));
+
+    Ok(())
+  }
+
+  /// Attaches a service control policy to a node of the organization tree
+  ///
+  /// A request is only allowed if, at every level of the path from the root
+  /// to the account of the principal, one of the attached service control
+  /// policies allows it. Policies attached outside of that path are ignored.
+  ///
+  /// Unlike AWS, which requires at least one attached policy at every level,
+  /// levels without any attached policy do not restrict requests.
+  ///
+  /// The account is read from the `aws:PrincipalAccount` context key, or
+  /// extracted from the principal ARN. Once the tree has any node, requests
+  /// from accounts that are not part of it, or whose account cannot be
+  /// resolved, are denied.
+  pub fn add_service_control_policy<R>(&mut self, node: &str, input: R) -> Result<(), Error>
+  where
+    R: io::Read,
+  {
+    if !self.organization.contains_key(node) {
+      return Err(Error::UnknownOrganizationNode(node.to_string()));
+    }
+
+    self.add_policy_with_type(PolicyType::ServiceControl, input)?;
+
+    if let Some(policy) = self.policies.last_mut() {
+      policy.node = Some(node.to_string());
+    }
+
+    Ok(())
+  }
+
+  /// Appends the trust policy of a role to the evaluator
+  ///
+  /// The trust policy is added as a [`PolicyType::Resource`] policy that only
+  /// applies to requests whose resource is `role`, such as the ones built by
+  /// [`Evaluator::assume_role()`], so that several roles can be trusted by
+  /// the same evaluator. As in AWS, trust policy statements usually have no
+  /// `Resource` element: `role` is used in its place.
+  pub fn add_trust_policy<R>(&mut self, role: &str, input: R) -> Result<(), Error>
+  where
+    R: io::Read,
+  {
+    sts::parse_role_arn(role)?;
+
+    let mut policy = parser::parse_trust_policy(input, role)?;
+
+    policy.id = self.policies.len();
+    policy.kind = PolicyType::Resource;
     self.policies.push(policy);
 
     Ok(())
@@ -112,19 +274,86 @@ impl Regoer {
     self.engine.add_data(data.into()).map_err(|err| Error::GenericError(err.to_string()))
   }
 
+  /// Registers the shape of resource identifiers starting with a given prefix
+  ///
+  /// Resources, `Arn*` conditions and account extraction use the scheme
+  /// matching the first segment of identifiers, replacing any scheme
+  /// previously registered for the same prefix. See [`ResourceScheme`].
+  pub fn add_resource_scheme(&mut self, scheme: ResourceScheme) {
+    self.schemes.insert(scheme.prefix.clone(), scheme);
+  }
+
+  /// Makes account root ARNs listed in `NotPrincipal` exempt all principals of
+  /// that account.
+  ///
+  /// By default, as in AWS, listing `arn:aws:iam::123456789012:root` in a
+  /// `NotPrincipal` element only exempts the account root user, so roles and
+  /// assumed-role sessions from that account need to be listed explicitly.
+  pub fn exempt_account_principals(&mut self, exempt: bool) {
+    self.exempt_account_principals = exempt;
+  }
+
   /// Compiles the added policies into an [`Evaluator`].
   ///
   /// This consumes the [`Regoer`] builder so it cannot be used anymore. If
   /// you need to build a new set of policies, create a new instance of
   /// [`Regoer`].
   pub fn compile(mut self) -> Result<Evaluator, Error> {
+    let organization = self.organization_paths();
+
+    self.add_data(json!({ "regoer": {
+      "options": { "exempt_account_principals": self.exempt_account_principals },
+      "organization": organization,
+      "schemes": self.schemes,
+    } }))?;
+
+    self.engine.add_policy("prelude.rego".into(), PRELUDE.into()).map_err(|err| Error::GenericError(err.to_string()))?;
+
+    let source_maps = self.policies.iter().map(Policy::source_map).collect::<Result<Vec<_>, _>>()?;
+
     for policy in &self.policies {
-      self.engine.add_policy("main.rego".into(), policy.serialize()?).map_err(|err| Error::GenericError(err.to_string()))?;
+      self
+        .engine
+        .add_policy(format!("{}.rego", policy.package()), policy.serialize()?)
+        .map_err(|err| rego_error(&source_maps, err))?;
     }
 
-    let policy = self.engine.compile_with_entrypoint(&Arc::from("data.main.allow")).unwrap();
+    self.engine.add_policy("main.rego".into(), MAIN.into()).map_err(|err| Error::GenericError(err.to_string()))?;
+
+    let policy = self.engine.compile_with_entrypoint(&Arc::from("data.regoer.decision")).map_err(|err| rego_error(&source_maps, err))?;
+
+    let explanation = self
+      .engine
+      .compile_with_entrypoint(&Arc::from("data.regoer.explanation"))
+      .map_err(|err| rego_error(&source_maps, err))?;
+
+    Ok(Evaluator {
+      policies: self.policies,
+      policy,
+      explanation,
+      source_maps,
+    })
+  }
+
+  /// Path from the root to every node of the organization tree.
+  fn organization_paths(&self) -> BTreeMap<&str, Vec<&str>> {
+    self
+      .organization
+      .keys()
+      .map(|node| {
+        let mut path = vec![node.as_str()];
+        let mut current = node;
+
+        while let Some(Some(parent)) = self.organization.get(current) {
+          path.push(parent.as_str());
+          current = parent;
+        }
 
-    Ok(Evaluator { policies: self.policies, policy })
+        path.reverse();
+
+        (node.as_str(), path)
+      })
+      .collect()
   }
 }
 
@@ -132,7 +361,8 @@ impl Evaluator {
   /// Evaluates the policy set.
   ///
   /// Run all the policies that were compiled into this evaluator, returning
-  /// a boolean on whether the request should be accepted or not.
+  /// a [`Decision`] on whether the request should be accepted or not, along
+  /// with the statements that caused it.
   ///
   /// It takes an object that implements [`Serialize`] and that takes the following
   /// shape:
@@ -147,30 +377,153 @@ impl Evaluator {
   /// });
   /// ```
   ///
+  /// `principal` can either be a plain identifier, matched as an `AWS` principal, or
+  /// an object carrying the principal type (`AWS`, `Service`, `Federated` or
+  /// `CanonicalUser`) and identifier, such as
+  /// `{ "type": "Service", "id": "lambda.amazonaws.com" }`.
+  ///
   /// Apart from `principal`, `action` and `resource`, this object is freeform, but
   /// should match what is expected from the compiled policies.
-  pub fn evaluate(&self, input: &impl Serialize) -> Result<bool, Error> {
-    let input: serde_json::Value = serde_json::to_value(input).map_err(|err| Error::GenericError(err.to_string()))?;
-    let result = self.policy.eval_with_input(input.into()).map_err(|err| Error::GenericError(err.to_string()))?;
+  ///
+  /// As condition key names are case-insensitive, keys of the condition key
+  /// namespaces are matched regardless of their case, so `aws:SourceIp` reads
+  /// `{ "aws": { "sourceip": ... } }`. Keys that only differ by their case,
+  /// such as two casings of a request tag, are rejected. Values, including tag
+  /// keys listed in `aws:TagKeys`, keep their case.
+  pub fn evaluate(&self, input: &impl Serialize) -> Result<Decision, Error> {
+    let input = normalize(serde_json::to_value(input).map_err(|err| Error::GenericError(err.to_string()))?)?;
+    let result = self.policy.eval_with_input(input.into()).map_err(|err| rego_error(&self.source_maps, err))?;
+
+    let mut decision: Decision = serde_json::from_value(serde_json::to_value(&result)?)?;
+    decision.statements.sort_by_key(|statement| (statement.policy, statement.statement));
 
-    result.as_bool().copied().map_err(|err| Error::GenericError(err.to_string()))
+    Ok(decision)
+  }
+
+  /// Evaluates the policy set, reporting how every statement fared.
+  ///
+  /// Along with the [`Decision`], the [`Explanation`] details, for every
+  /// statement of every policy, whether its principal, action and resource
+  /// matched the request, and which of its conditions passed, so that
+  /// unexpected decisions can be debugged.
+  ///
+  /// The input takes the same shape as for [`Evaluator::evaluate()`].
+  pub fn explain(&self, input: &impl Serialize) -> Result<Explanation, Error> {
+    let input = normalize(serde_json::to_value(input).map_err(|err| Error::GenericError(err.to_string()))?)?;
+    let result = self.explanation.eval_with_input(input.into()).map_err(|err| rego_error(&self.source_maps, err))?;
+
+    let mut explanation: Explanation = serde_json::from_value(serde_json::to_value(&result)?)?;
+    explanation.decision.statements.sort_by_key(|statement| (statement.policy, statement.statement));
+    explanation.policies.sort_by_key(|policy| policy.policy);
+
+    for policy in &mut explanation.policies {
+      policy.statements.sort_by_key(|statement| statement.statement);
+    }
+
+    Ok(explanation)
+  }
+
+  /// Evaluates a role assumption against the trust policy of the role.
+  ///
+  /// The trust policy is expected to be added with
+  /// [`Regoer::add_trust_policy()`], the role being the resource of the
+  /// request. Trust policies added as plain [`PolicyType::Resource`] policies
+  /// are not tied to a role, and apply to every role their `Resource` element
+  /// matches, any role for `"Resource": "*"`. For cross-account
+  /// requests, an identity-based policy of the caller allowing the STS action
+  /// must also be compiled into this evaluator. Federated principals only need
+  /// the trust policy.
+  ///
+  /// On success, the derived [`Session`] can act as the principal of further
+  /// requests, through [`Session::request()`], or assume another role.
+  pub fn assume_role(&self, request: AssumeRole) -> Result<Session, Error> {
+    let session = request.session()?;
+    let decision = self.evaluate(&request.input()?)?;
+
+    match decision.is_allowed() {
+      true => Ok(session),
+      false => Err(Error::AssumeRoleDenied(decision)),
+    }
   }
 
   /// Get the policies compiled into this evaluator.
   pub fn rego(&self) -> &[Policy] {
     &self.policies
   }
+
+  /// Get the source maps of the compiled policies, in the same order as [`Evaluator::rego()`].
+  pub fn source_maps(&self) -> &[SourceMap] {
+    &self.source_maps
+  }
+}
+
+/// Wraps an engine error, locating it in the policy documents when it refers to a generated rule.
+fn rego_error(source_maps: &[SourceMap], err: impl fmt::Display) -> Error {
+  let message = err.to_string();
+
+  match source_maps.iter().find_map(|source_map| source_map.resolve(&message)) {
+    Some(location) => Error::RegoError(location.clone(), message),
+    None => Error::GenericError(message),
+  }
+}
+
+/// Lowercases condition key names of the input, for case-insensitive key resolution.
+///
+/// Apart from `principal`, `action` and `resource`, top-level entries are
+/// condition key namespaces, whose nested object keys are lowercased.
+fn normalize(value: serde_json::Value) -> Result<serde_json::Value, Error> {
+  let serde_json::Value::Object(object) = value else {
+    return Ok(value);
+  };
+
+  let mut normalized = serde_json::Map::new();
+
+  for (key, value) in object {
+    let key = key.to_lowercase();
+
+    let value = match key.as_str() {
+      "principal" | "action" | "resource" => value,
+      _ => lowercase_keys(value)?,
+    };
+
+    if normalized.insert(key.clone(), value).is_some() {
+      return Err(Error::DuplicateInputKey(key));
+    }
+  }
+
+  Ok(serde_json::Value::Object(normalized))
+}
+
+/// Lowercases object keys recursively, rejecting keys only differing by case.
+fn lowercase_keys(value: serde_json::Value) -> Result<serde_json::Value, Error> {
+  let serde_json::Value::Object(object) = value else {
+    return Ok(value);
+  };
+
+  let mut normalized = serde_json::Map::new();
+
+  for (key, value) in object {
+    let key = key.to_lowercase();
+
+    if normalized.insert(key.clone(), lowercase_keys(value)?).is_some() {
+      return Err(Error::DuplicateInputKey(key));
+    }
+  }
+
+  Ok(serde_json::Value::Object(normalized))
 }
 
 impl fmt::Display for Evaluator {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{}", PRELUDE)?;
+
     for policy in &self.policies {
       match policy.serialize() {
-        Ok(output) => write!(f, "{}", output)?,
-        Err(err) => write!(f, "// ERROR: {}", err)?,
+        Ok(output) => writeln!(f, "{}", output)?,
+        Err(err) => writeln!(f, "// ERROR: {}", err)?,
       }
     }
 
-    Ok(())
+    write!(f, "{}", MAIN)
   }
 }
@@ -0,0 +1,424 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+  conditions::operator_name,
+  location::ErrorLocation,
+  parser::{Error, parse_json, read_statements},
+  source,
+  statement::{Statement, element},
+  values::{Effect, Principal, Scope, Value},
+};
+
+/// Global condition keys, available to every service.
+const GLOBAL_KEYS: &[&str] = &[
+  "calledvia",
+  "calledviafirst",
+  "calledvialast",
+  "currenttime",
+  "ec2instancesourceprivateipv4",
+  "ec2instancesourcevpc",
+  "epochtime",
+  "federatedprovider",
+  "multifactorauthage",
+  "multifactorauthpresent",
+  "principalaccount",
+  "principalarn",
+  "principalisawsservice",
+  "principalorgid",
+  "principalorgpaths",
+  "principalservicename",
+  "principalservicenameslist",
+  "principaltype",
+  "referer",
+  "requestedregion",
+  "resourceaccount",
+  "resourceorgid",
+  "resourceorgpaths",
+  "securetransport",
+  "sourceaccount",
+  "sourcearn",
+  "sourceidentity",
+  "sourceip",
+  "sourceorgid",
+  "sourceorgpaths",
+  "sourceowner",
+  "sourcevpc",
+  "sourcevpce",
+  "sourcevpcarn",
+  "tagkeys",
+  "tokenissuetime",
+  "useragent",
+  "userid",
+  "username",
+  "viaawsservice",
+  "vpcsourceip",
+];
+
+/// Global condition keys suffixed with a tag name, such as `aws:PrincipalTag/team`.
+const GLOBAL_TAG_KEYS: &[&str] = &["principaltag/", "requesttag/", "resourcetag/"];
+
+/// Kind of issue reported by [`Regoer::lint()`](crate::Regoer::lint()).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum FindingCode {
+  /// `Allow` statement granting every action on every resource.
+  AllowAll,
+  /// `NotAction` in an `Allow` statement, granting every action but the listed ones.
+  AllowNotAction,
+  /// `Allow` statement granting access to any principal, through a wildcard
+  /// or `NotPrincipal`, without any condition.
+  PublicPrincipal,
+  /// Statement already covered by another statement with the same effect.
+  RedundantStatement,
+  /// `Allow` statement entirely covered by an unconditional `Deny` statement.
+  ShadowedStatement,
+  /// Condition key which is probably not set on requests to the services of
+  /// the statement actions.
+  ///
+  /// This is a heuristic: `aws:` keys are checked against a list of global
+  /// keys, and other keys against the service prefixes of the actions, so
+  /// valid keys of other services, such as `kms:ViaService` in an `s3`
+  /// statement, are reported as well.
+  UnknownConditionKey,
+  /// Resource or principal ARN not shaped as `arn:partition:service:region:account:resource`.
+  MalformedArn,
+  /// `Sid` used by several statements.
+  DuplicateSid,
+}
+
+impl FindingCode {
+  /// Stable identifier of the finding, such as `ALLOW_ALL`.
+  pub fn code(&self) -> &'static str {
+    match self {
+      FindingCode::AllowAll => "ALLOW_ALL",
+      FindingCode::AllowNotAction => "ALLOW_NOT_ACTION",
+      FindingCode::PublicPrincipal => "PUBLIC_PRINCIPAL",
+      FindingCode::RedundantStatement => "REDUNDANT_STATEMENT",
+      FindingCode::ShadowedStatement => "SHADOWED_STATEMENT",
+      FindingCode::UnknownConditionKey => "UNKNOWN_CONDITION_KEY",
+      FindingCode::MalformedArn => "MALFORMED_ARN",
+      FindingCode::DuplicateSid => "DUPLICATE_SID",
+    }
+  }
+
+  /// Severity findings of this kind are reported with.
+  pub fn severity(&self) -> FindingSeverity {
+    match self {
+      FindingCode::MalformedArn | FindingCode::DuplicateSid => FindingSeverity::Error,
+      FindingCode::AllowAll | FindingCode::AllowNotAction | FindingCode::PublicPrincipal => FindingSeverity::Security,
+      FindingCode::ShadowedStatement => FindingSeverity::Warning,
+      FindingCode::RedundantStatement | FindingCode::UnknownConditionKey => FindingSeverity::Suggestion,
+    }
+  }
+}
+
+impl fmt::Display for FindingCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.code())
+  }
+}
+
+/// Severity of a lint [`Finding`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FindingSeverity {
+  /// The policy would be rejected by AWS.
+  Error,
+  /// The policy grants more access than is probably intended.
+  Security,
+  /// The policy probably does not behave as intended.
+  Warning,
+  /// The policy can be simplified, or may not behave as intended.
+  Suggestion,
+}
+
+impl fmt::Display for FindingSeverity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FindingSeverity::Error => write!(f, "error"),
+      FindingSeverity::Security => write!(f, "security warning"),
+      FindingSeverity::Warning => write!(f, "warning"),
+      FindingSeverity::Suggestion => write!(f, "suggestion"),
+    }
+  }
+}
+
+/// Security or quality issue found in a policy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Finding {
+  pub code: FindingCode,
+  pub severity: FindingSeverity,
+  pub message: String,
+  pub location: ErrorLocation,
+}
+
+impl fmt::Display for Finding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}[{}]: {}\n{}", self.severity, self.code, self.message, self.location)
+  }
+}
+
+/// Reads the statements of a policy document and lints them.
+pub(crate) fn lint_document(source: &str) -> Result<Vec<Finding>, Error> {
+  let resolve = |mut errors: Vec<Error>| errors.remove(0).resolve(source);
+
+  let document = parse_json(source)?;
+  let (_, statements) = read_statements(&document).map_err(resolve)?;
+  let statements: Vec<_> = statements.into_iter().collect::<Result<_, _>>().map_err(resolve)?;
+
+  Ok(lint(&statements, &document, source))
+}
+
+/// Runs every check over the statements of a policy, in document order.
+fn lint(statements: &[Statement], document: &serde_json::Value, source: &str) -> Vec<Finding> {
+  let mut findings = vec![];
+  let mut sids: HashMap<&str, usize> = HashMap::new();
+
+  for statement in statements {
+    let mut finding = |code: FindingCode, location: ErrorLocation, message: String| {
+      findings.push(Finding {
+        code,
+        severity: code.severity(),
+        message,
+        location: location.resolve(source),
+      });
+    };
+
+    let location = |pointer: String| ErrorLocation::statement(statement.index, statement.sid.as_deref(), pointer);
+    let at = |element: &str| source::pointer(&statement.pointer, element);
+    let allow = statement.effect == Effect::Allow;
+
+    if allow && is_wildcard(&statement.actions) && is_wildcard(&statement.resources) {
+      finding(FindingCode::AllowAll, location(statement.pointer.clone()), "statement allows all actions on all resources".into());
+    }
+
+    if allow && let Scope::Not(_) = statement.actions {
+      finding(
+        FindingCode::AllowNotAction,
+        location(at("NotAction")),
+        "NotAction with Allow grants every action not listed, including those of any other service".into(),
+      );
+    }
+
+    let public = match &statement.principals {
+      Some(Scope::Id(principals)) => {
+        let public = principals.as_slice().iter().any(Principal::is_wildcard);
+        public.then_some("statement allows any principal, without any condition")
+      }
+      Some(Scope::Not(principals)) => {
+        let public = !principals.as_slice().iter().any(Principal::is_wildcard);
+        public.then_some("NotPrincipal with Allow grants every principal not listed, without any condition")
+      }
+      None => None,
+    };
+
+    if allow
+      && let Some(message) = public
+      && let Some(scope) = &statement.principals
+      && statement.conditions.is_empty()
+    {
+      finding(FindingCode::PublicPrincipal, location(at(element(scope, "Principal", "NotPrincipal"))), message.into());
+    }
+
+    let services = services(&statement.actions);
+
+    for (operator, pairs) in &statement.conditions {
+      for (key, _) in pairs {
+        let message = match key.qualifier().as_deref().map(str::to_lowercase) {
+          Some(qualifier) if qualifier == "aws" && !is_global_key(key.value()) => format!("{key} is not a known global condition key"),
+          Some(qualifier) if qualifier == "aws" || qualifier == "saml" => continue,
+          Some(qualifier) if services.as_ref().is_some_and(|services| !services.contains(&qualifier)) => {
+            format!("{key} may not apply to the actions of the statement")
+          }
+          _ => continue,
+        };
+
+        let pointer = source::condition_pointer(&statement.pointer, operator, key);
+        finding(FindingCode::UnknownConditionKey, location(pointer).condition(operator_name(operator), key.to_string()), message);
+      }
+    }
+
+    let resources = element(&statement.resources, "Resource", "NotResource");
+
+    for id in statement.resources.as_slice().iter().filter(|id| is_malformed_arn(id)) {
+      finding(FindingCode::MalformedArn, location(item(document, &at(resources), id)), malformed_arn(id));
+    }
+
+    if let Some(scope) = &statement.principals {
+      let principals = source::pointer(&at(element(scope, "Principal", "NotPrincipal")), "AWS");
+
+      for principal in scope.as_slice().iter().filter(|principal| principal.kind() == "AWS" && is_malformed_arn(&principal.id)) {
+        finding(FindingCode::MalformedArn, location(item(document, &principals, &principal.id)), malformed_arn(&principal.id));
+      }
+    }
+
+    let others = || statements.iter().filter(|other| other.index != statement.index);
+    let shadowing = others().find(|other| other.effect == Effect::Deny && other.conditions.is_empty() && covers(other, statement));
+    // Of two identical statements, only the latter is reported.
+    let covering = others().find(|other| is_redundant(statement, other) && !(is_redundant(other, statement) && statement.index < other.index));
+
+    if allow && let Some(other) = shadowing {
+      let message = format!("statement is entirely denied by statement {}", other.index);
+      finding(FindingCode::ShadowedStatement, location(statement.pointer.clone()), message);
+    } else if let Some(other) = covering {
+      let message = format!("statement is already covered by statement {}", other.index);
+      finding(FindingCode::RedundantStatement, location(statement.pointer.clone()), message);
+    }
+
+    if let Some(sid) = statement.sid.as_deref() {
+      match sids.get(sid) {
+        Some(first) => finding(FindingCode::DuplicateSid, location(at("Sid")), format!("Sid {sid} is already used by statement {first}")),
+        None => {
+          sids.insert(sid, statement.index);
+        }
+      }
+    }
+  }
+
+  findings
+}
+
+/// Whether the scope includes every identifier.
+fn is_wildcard(scope: &Scope<String>) -> bool {
+  matches!(scope, Scope::Id(ids) if ids.as_slice().iter().any(|id| id == "*"))
+}
+
+/// Services targeted by the actions of a statement, if they can be told.
+fn services(actions: &Scope<String>) -> Option<Vec<String>> {
+  let Scope::Id(actions) = actions else {
+    return None;
+  };
+
+  actions
+    .as_slice()
+    .iter()
+    .map(|action| action.split_once(':').map(|(service, _)| service.to_lowercase()).filter(|service| !service.contains(['*', '?'])))
+    .collect()
+}
+
+fn is_global_key(key: &str) -> bool {
+  let key = key.to_lowercase();
+
+  GLOBAL_KEYS.contains(&key.as_str()) || GLOBAL_TAG_KEYS.iter().any(|prefix| key.starts_with(prefix))
+}
+
+fn is_malformed_arn(id: &str) -> bool {
+  let Some(arn) = id.strip_prefix("arn:") else {
+    return false;
+  };
+
+  let segments: Vec<_> = arn.splitn(5, ':').collect();
+
+  segments.len() < 5 || segments[0].is_empty() || segments[1].is_empty()
+}
+
+fn malformed_arn(id: &str) -> String {
+  format!("{id} is not shaped as arn:partition:service:region:account:resource")
+}
+
+/// Pointer to the item holding `id` in an element, which may hold a single value or a list.
+fn item(document: &serde_json::Value, pointer: &str, id: &str) -> String {
+  let list = document.pointer(pointer).and_then(serde_json::Value::as_array);
+
+  match list.and_then(|list| list.iter().position(|item| item == id)) {
+    Some(index) => source::pointer(pointer, &index.to_string()),
+    None => pointer.to_string(),
+  }
+}
+
+/// Whether `statement` has no effect, given `other`: both have the same
+/// effect, `other` applies to every request `statement` applies to, and does
+/// not have conditions of its own.
+fn is_redundant(statement: &Statement, other: &Statement) -> bool {
+  statement.effect == other.effect && (other.conditions.is_empty() || other.conditions == statement.conditions) && covers(other, statement)
+}
+
+/// Whether `statement` applies to every principal, action and resource `other` applies to.
+fn covers(statement: &Statement, other: &Statement) -> bool {
+  // Statements without a `Principal` element apply to any principal, as wildcards do.
+  let any = |principals: &Option<Scope<Principal>>| match principals {
+    None => true,
+    Some(Scope::Id(principals)) => principals.as_slice().iter().any(Principal::is_wildcard),
+    Some(Scope::Not(_)) => false,
+  };
+
+  let principals = match (&statement.principals, &other.principals) {
+    (principals, _) if any(principals) => true,
+    (Some(Scope::Id(principals)), Some(Scope::Id(others))) => others.as_slice().iter().all(|principal| principals.as_slice().contains(principal)),
+    (Some(principals), Some(others)) => principals == others,
+    _ => false,
+  };
+
+  principals && scope_covers(&statement.actions, &other.actions, true) && scope_covers(&statement.resources, &other.resources, false)
+}
+
+fn scope_covers(scope: &Scope<String>, other: &Scope<String>, ignore_case: bool) -> bool {
+  let normalize = |id: &String| match ignore_case {
+    true => id.to_lowercase(),
+    false => id.clone(),
+  };
+
+  let includes = |patterns: &Value<String>, ids: &Value<String>| ids.as_slice().iter().all(|id| patterns.as_slice().iter().any(|pattern| covered(&normalize(pattern), &normalize(id))));
+
+  match (scope, other) {
+    (Scope::Id(patterns), Scope::Id(ids)) => includes(patterns, ids),
+    // Excluding fewer identifiers applies to more of them.
+    (Scope::Not(ids), Scope::Not(patterns)) => includes(patterns, ids),
+    _ => false,
+  }
+}
+
+/// Whether every identifier matched by `id`, itself possibly a pattern, is
+/// matched by `pattern`.
+///
+/// Wildcards are only matched within a segment, except the last one, so
+/// coverage is never assumed across ARN segments.
+fn covered(pattern: &str, id: &str) -> bool {
+  if pattern == "*" {
+    return true;
+  }
+
+  let patterns: Vec<_> = pattern.splitn(6, ':').collect();
+  let ids: Vec<_> = id.splitn(6, ':').collect();
+
+  patterns.len() == ids.len() && patterns.iter().zip(&ids).all(|(pattern, id)| glob(pattern.as_bytes(), id.as_bytes()))
+}
+
+/// Matches a value against a pattern, a `*` or `?` in the value only being
+/// matched by a `*` of the pattern.
+fn glob(pattern: &[u8], value: &[u8]) -> bool {
+  match (pattern.first(), value.first()) {
+    (Some(b'*'), _) => glob(&pattern[1..], value) || (!value.is_empty() && glob(pattern, &value[1..])),
+    (Some(b'?'), Some(c)) => !matches!(c, b'*' | b'?') && glob(&pattern[1..], &value[1..]),
+    (Some(p), Some(c)) => p == c && glob(&pattern[1..], &value[1..]),
+    (None, None) => true,
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{covered, is_malformed_arn};
+
+  #[test]
+  fn coverage() {
+    assert!(covered("*", "arn:aws:s3:::bucket/*"));
+    assert!(covered("s3:*", "s3:getobject"));
+    assert!(covered("s3:get*", "s3:get*"));
+    assert!(covered("arn:aws:s3:::bucket/*", "arn:aws:s3:::bucket/key:with:colons"));
+
+    assert!(!covered("s3:get*", "s3:*"));
+    assert!(!covered("s3:?etobject", "s3:*etobject"));
+    assert!(!covered("arn:aws:*", "arn:aws:s3:::bucket"));
+    assert!(!covered("s3:getobject", "s3:putobject"));
+  }
+
+  #[test]
+  fn arns() {
+    assert!(!is_malformed_arn("arn:aws:s3:::bucket"));
+    assert!(!is_malformed_arn("arn:aws:logs:us-east-1:123456789012:log-group:app:*"));
+    assert!(!is_malformed_arn("*"));
+
+    assert!(is_malformed_arn("arn:aws:s3:bucket"));
+    assert!(is_malformed_arn("arn::s3:::bucket"));
+    assert!(is_malformed_arn("arn:aws::::bucket"));
+  }
+}
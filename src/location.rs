@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde_json::value::RawValue;
+
+/// Position of an error in a policy document.
+///
+/// Fields are filled as far as they are known: syntax errors only carry a
+/// line and column, while errors raised when transpiling a statement carry
+/// the statement, and the condition operator and key if they concern one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ErrorLocation {
+  /// Index of the statement within its policy.
+  pub statement: Option<usize>,
+  /// `Sid` of the statement, if any.
+  pub sid: Option<String>,
+  /// Condition operator, such as `StringEquals`.
+  pub operator: Option<String>,
+  /// Condition key, such as `aws:userid`.
+  pub key: Option<String>,
+  /// JSON pointer to the offending element, such as `/Statement/0/Condition/StringEquals/aws:userid`.
+  pub pointer: Option<String>,
+  /// Line of the offending element in the document, starting from 1.
+  pub line: Option<usize>,
+  /// Column of the offending element in the document, starting from 1.
+  pub column: Option<usize>,
+  snippet: Option<String>,
+}
+
+impl ErrorLocation {
+  pub(crate) fn statement(index: usize, sid: Option<&str>, pointer: String) -> ErrorLocation {
+    ErrorLocation {
+      statement: Some(index),
+      sid: sid.map(ToString::to_string),
+      pointer: Some(pointer),
+      ..ErrorLocation::default()
+    }
+  }
+
+  pub(crate) fn pointer(pointer: &str) -> ErrorLocation {
+    ErrorLocation {
+      pointer: Some(pointer.to_string()),
+      ..ErrorLocation::default()
+    }
+  }
+
+  pub(crate) fn condition(mut self, operator: String, key: String) -> ErrorLocation {
+    self.operator = Some(operator);
+    self.key = Some(key);
+    self
+  }
+
+  /// Location of a given line and column of the document.
+  pub(crate) fn position(document: &str, line: usize, column: usize) -> ErrorLocation {
+    ErrorLocation::default().at(document, line, column)
+  }
+
+  /// Resolves the line and column of the element targeted by the pointer, if it can be found in the document.
+  pub(crate) fn resolve(self, document: &str) -> ErrorLocation {
+    let Some(offset) = self.pointer.as_deref().and_then(|pointer| find(document, pointer)) else {
+      return self;
+    };
+
+    let line = document[..offset].matches('\n').count() + 1;
+    let column = document[..offset].rsplit('\n').next().map_or(0, |prefix| prefix.chars().count()) + 1;
+
+    self.at(document, line, column)
+  }
+
+  fn at(mut self, document: &str, line: usize, column: usize) -> ErrorLocation {
+    self.line = Some(line);
+    self.column = Some(column);
+    self.snippet = document.lines().nth(line.saturating_sub(1)).map(|text| text.trim_end().to_string());
+    self
+  }
+
+  /// Line of the document the error points to.
+  pub fn snippet(&self) -> Option<&str> {
+    self.snippet.as_deref()
+  }
+}
+
+impl fmt::Display for ErrorLocation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut parts = vec![];
+
+    if let (Some(line), Some(column)) = (self.line, self.column) {
+      parts.push(format!("line {line}, column {column}"));
+    }
+
+    match (self.statement, &self.sid) {
+      (Some(index), Some(sid)) => parts.push(format!("statement {index} ({sid})")),
+      (Some(index), None) => parts.push(format!("statement {index}")),
+      _ => {}
+    }
+
+    if let (Some(operator), Some(key)) = (&self.operator, &self.key) {
+      parts.push(format!("condition {operator} {key}"));
+    }
+
+    write!(f, "--> {}", parts.join(", "))?;
+
+    if let (Some(line), Some(column), Some(snippet)) = (self.line, self.column, &self.snippet) {
+      let gutter = " ".repeat(line.to_string().len());
+      let caret = " ".repeat(column.saturating_sub(1).min(snippet.chars().count()));
+
+      write!(f, "\n{gutter} |\n{line} | {snippet}\n{gutter} | {caret}^")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Byte offset of the element targeted by a JSON pointer: the name of object
+/// members, or the value of array items.
+///
+/// Member names are compared case-insensitively, as condition operators and
+/// keys are.
+fn find(document: &str, pointer: &str) -> Option<usize> {
+  let mut value: &RawValue = serde_json::from_str(document).ok()?;
+  let mut offset = value.get().as_ptr() as usize - document.as_ptr() as usize;
+
+  for segment in pointer.split('/').skip(1).map(|segment| segment.replace("~1", "/").replace("~0", "~")) {
+    match value.get().as_bytes().first()? {
+      b'{' => {
+        let members: BTreeMap<String, &RawValue> = serde_json::from_str(value.get()).ok()?;
+
+        value = members.iter().find(|(name, _)| name.eq_ignore_ascii_case(&segment)).map(|(_, value)| *value)?;
+        offset = member(document, value.get().as_ptr() as usize - document.as_ptr() as usize)?;
+      }
+
+      b'[' => {
+        let items: Vec<&RawValue> = serde_json::from_str(value.get()).ok()?;
+
+        value = items.get(segment.parse::<usize>().ok()?)?;
+        offset = value.get().as_ptr() as usize - document.as_ptr() as usize;
+      }
+
+      _ => return None,
+    }
+  }
+
+  Some(offset)
+}
+
+/// Byte offset of the name of the object member whose value starts at `value`.
+fn member(document: &str, value: usize) -> Option<usize> {
+  let name = document[..value].trim_end().strip_suffix(':')?.trim_end().strip_suffix('"')?;
+  let mut end = name.len();
+
+  // The opening quote is the first one, going backwards, not escaped by a backslash.
+  loop {
+    let quote = name[..end].rfind('"')?;
+    let escapes = quote - name[..quote].trim_end_matches('\\').len();
+
+    if escapes % 2 == 0 {
+      return Some(quote);
+    }
+
+    end = quote;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ErrorLocation, find};
+
+  const DOCUMENT: &str = r#"{
+  "Version": "2012-10-17",
+  "Statement": [
+    { "Sid": "A", "Effect": "Allow", "Action": ["s3:*", "ec2:*"], "Resource": "*" },
+    {
+      "Sid": "B\"{",
+      "Effect": "Deny",
+      "Condition": { "stringequals": { "aws:PrincipalTag/team": 12 } }
+    }
+  ]
+}"#;
+
+  #[test]
+  fn pointers() {
+    let at = |pointer: &str| find(DOCUMENT, pointer).map(|offset| DOCUMENT[offset..].split_once(':').map_or("", |(head, _)| head));
+
+    assert_eq!(find(DOCUMENT, ""), Some(0));
+    assert_eq!(at("/Version"), Some(r#""Version""#));
+    assert_eq!(at("/Statement/0"), Some(r#"{ "Sid""#));
+    assert_eq!(at("/Statement/0/Action"), Some(r#""Action""#));
+    assert_eq!(at("/Statement/1/Condition/StringEquals/aws:PrincipalTag~1team"), Some(r#""aws"#));
+    assert_eq!(at("/Statement/2"), None);
+    assert_eq!(at("/Statement/1/Resource"), None);
+
+    let document = r#"{ "x\\": 1, "a\"": 2 }"#;
+
+    assert_eq!(find(document, "/x\\"), Some(2));
+    assert_eq!(find(document, "/a\""), Some(12));
+  }
+
+  #[test]
+  fn display() {
+    let location = ErrorLocation::statement(1, Some("B"), "/Statement/1/Condition/StringEquals/aws:PrincipalTag~1team".into())
+      .condition("StringEquals".into(), "aws:PrincipalTag/team".into())
+      .resolve(DOCUMENT);
+
+    assert_eq!((location.line, location.column), (Some(8), Some(40)));
+    assert_eq!(
+      location.to_string(),
+      [
+        "--> line 8, column 40, statement 1 (B), condition StringEquals aws:PrincipalTag/team",
+        "  |",
+        r#"8 |       "Condition": { "stringequals": { "aws:PrincipalTag/team": 12 } }"#,
+        "  |                                        ^",
+      ]
+      .join("\n")
+    );
+
+    assert_eq!(ErrorLocation::statement(0, None, "/Statement/3".into()).resolve(DOCUMENT).to_string(), "--> statement 0");
+  }
+}
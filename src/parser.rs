@@ -1,36 +1,255 @@
-use std::{fmt::Write, io};
+use std::{collections::HashMap, fmt::Write, io};
 
-use aws_iam::model::{Action, Effect as AwsEffect, OneOrAll, OneOrAny, Principal, QString, Resource};
+use aws_iam::model::{Action, Effect as AwsEffect, OneOrAll, OneOrAny, Policy as AwsPolicy, Principal, PrincipalType, Resource, Statement as AwsStatement, Version as AwsVersion};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-  expression::{Expr, Repr},
+  conditions::{build_condition, operator_name},
+  decision::Decision,
+  expression::{Expr, Repr, quote},
   interpolation::SubstitutionError,
+  location::ErrorLocation,
+  source::{self, SourceLocation, SourceMap},
   statement::Statement,
-  values::{Effect, Scope, Value},
+  values::{Effect, Principal as StatementPrincipal, Scope, Value, Version},
 };
 
-const BASE: &str = r#"package main
-default allow = false
-default deny = false
-default permit = false
+pub const PRELUDE: &str = r#"package regoer.prelude
 to_array(x) := x if { is_array(x) }
 to_array(x) := [x] if { not is_array(x) }
+key_present(path) if {
+  value := object.get(input, path, null)
+  value != null
+  value != []
+}
+key_null(path) := false if { key_present(path) } else := true
+if_exists(path) := [path] if { key_present(path) } else := []
+principal_type := input.principal.type if { is_object(input.principal) } else := "AWS"
+principal_id := input.principal.id if { is_object(input.principal) } else := input.principal
+principal_is(kind, id) if {
+  kind == principal_type
+  id == principal_id
+}
+principal_is(kind, id) if {
+  kind == principal_type
+  contains(id, "*")
+  glob.match(id, null, principal_id)
+}
+principal_match(kind, id) if { principal_is(kind, id) }
+principal_match("AWS", id) if {
+  principal_type == "AWS"
+  account_principal(id) == principal_account
+}
+principal_match("AWS", id) if {
+  principal_type == "AWS"
+  glob.match(role_sessions(id), [":", "/"], principal_id)
+}
+principal_in(principals) if {
+  some [kind, id] in principals
+  principal_match(kind, id)
+}
+principal_named(principals) if {
+  some [kind, id] in principals
+  kind == principal_type
+  id == principal_id
+}
+account_id(id) if {
+  count(id) == 12
+  every c in split(id, "") { indexof("0123456789", c) >= 0 }
+}
+account_principal(id) := id if { account_id(id) }
+account_principal(id) := parts[4] if {
+  parts := split(id, ":")
+  count(parts) == 6
+  parts[0] == "arn"
+  parts[2] == "iam"
+  parts[5] == "root"
+}
+role_sessions(id) := sprintf("arn:%s:sts::%s:assumed-role/%s/*", [parts[1], parts[4], name]) if {
+  parts := split(id, ":")
+  count(parts) == 6
+  parts[0] == "arn"
+  parts[2] == "iam"
+  startswith(parts[5], "role/")
+  path := split(parts[5], "/")
+  name := path[count(path) - 1]
+}
+arn_account(arn) := account if {
+  scheme := data.regoer.schemes[split(arn, ":")[0]]
+  account := arn_parts(arn)[scheme.account]
+  account != ""
+}
+principal_account := principal_id if { account_id(principal_id) } else := arn_account(principal_id)
+principal_excluded(principals) if {
+  some [kind, id] in principals
+  principal_is(kind, id)
+}
+principal_excluded(principals) if {
+  data.regoer.options.exempt_account_principals == true
+  some ["AWS", id] in principals
+  parts := split(id, ":")
+  count(parts) == 6
+  parts[5] == "root"
+  parts[4] == principal_account
+}
+base64_alphabet := "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+base64_char(n) := substring(base64_alphabet, n, 1)
+base64_chunk([a, b, c]) := concat("", [base64_char(bits.rsh(a, 2)), base64_char(bits.or(bits.lsh(bits.and(a, 3), 4), bits.rsh(b, 4))), base64_char(bits.or(bits.lsh(bits.and(b, 15), 2), bits.rsh(c, 6))), base64_char(bits.and(c, 63))])
+base64_chunk([a, b]) := concat("", [base64_char(bits.rsh(a, 2)), base64_char(bits.or(bits.lsh(bits.and(a, 3), 4), bits.rsh(b, 4))), base64_char(bits.lsh(bits.and(b, 15), 2))])
+base64_chunk([a]) := concat("", [base64_char(bits.rsh(a, 2)), base64_char(bits.lsh(bits.and(a, 3), 4))])
+base64_encode(bytes) := concat("", [base64_chunk(array.slice(bytes, i, i + 3)) | some i in numbers.range_step(0, count(bytes) - 1, 3)])
+to_base64(x) := trim_right(trim_space(x), "=") if { is_string(x) }
+to_base64(x) := base64_encode(x) if { is_array(x) }
+arn_parts(arn) := array.concat(array.slice(parts, 0, last), [concat(":", array.slice(parts, last, count(parts)))]) if {
+  parts := split(arn, ":")
+  last := data.regoer.schemes[parts[0]].segments - 1
+  count(parts) > last
+}
 arn_like(lhs, rhs) if {
-  count(indexof_n(lhs, ":")) == 5
-  count(indexof_n(rhs, ":")) == 5
-  glob.match(lhs, [":"], rhs)
+  patterns := arn_parts(lhs)
+  segments := arn_parts(rhs)
+  patterns[0] == segments[0]
+  every i, pattern in patterns { glob.match(pattern, null, segments[i]) }
+}
+glob_escape(value) := escaped if {
+  is_string(value)
+  escaped := concat("", [glob_char(c) | c := split(value, "")[_]])
+} else := value
+glob_char(c) := concat("", ["\\", c]) if { c in {"\\", "[", "]", "{", "}", "*", "?"} } else := c
+resource_match(pattern, resource) if { arn_like(pattern, resource) }
+resource_match(pattern, resource) if {
+  not arn_parts(pattern)
+  glob.match(pattern, null, resource)
+}
+"#;
+
+pub const MAIN: &str = r#"package regoer
+default allow := false
+default deny := false
+default permit := false
+account := input.aws.principalaccount if { is_string(input.aws.principalaccount) } else := data.regoer.prelude.principal_account
+resource_account := input.aws.resourceaccount if { is_string(input.aws.resourceaccount) } else := data.regoer.prelude.arn_account(input.resource)
+cross_account if { account != resource_account }
+organized if { count(data.regoer.organization) > 0 }
+organization_path := path if { path := data.regoer.organization[account] }
+organization_path := [] if { not organized }
+scoped(policy) if { object.get(policy, "role", null) == null }
+scoped(policy) if { policy.role == input.resource }
+applies(policy) if {
+  object.get(policy, "node", null) == null
+  scoped(policy)
+}
+applies(policy) if { policy.node in organization_path }
+matched contains statement if {
+  some policy in data.regoer.policies
+  applies(policy)
+  some statement in policy.matched
+}
+allowed contains statement if {
+  some statement in matched
+  statement.effect == "Allow"
+}
+denied contains statement if {
+  some statement in matched
+  statement.effect == "Deny"
 }
+present(kind) if {
+  some policy in data.regoer.policies
+  policy.type == kind
+}
+granted(kind) if {
+  some policy in data.regoer.policies
+  policy.type == kind
+  applies(policy)
+  some statement in policy.matched
+  statement.effect == "Allow"
+}
+named(kind) if {
+  some policy in data.regoer.policies
+  policy.type == kind
+  applies(policy)
+  some statement in object.get(policy, "named", set())
+  statement.effect == "Allow"
+}
+bounded(kind) if { not present(kind) }
+bounded(kind) if { granted(kind) }
+attached(node) if {
+  some policy in data.regoer.policies
+  policy.type == "ServiceControl"
+  object.get(policy, "node", null) == node
+}
+controlled_by(node) if { not attached(node) }
+controlled_by(node) if {
+  some policy in data.regoer.policies
+  policy.type == "ServiceControl"
+  object.get(policy, "node", null) == node
+  some statement in policy.matched
+  statement.effect == "Allow"
+}
+controlled if {
+  every node in array.concat([null], organization_path) { controlled_by(node) }
+}
+identity_permit if {
+  granted("Identity")
+  bounded("PermissionBoundary")
+  bounded("Session")
+}
+resource_principal if {
+  data.regoer.prelude.principal_type == "AWS"
+  some pattern in ["arn:*:iam::*:user/*", "arn:*:sts::*:assumed-role/*"]
+  glob.match(pattern, [":"], data.regoer.prelude.principal_id)
+}
+resource_permit if {
+  named("Resource")
+  resource_principal
+}
+resource_permit if {
+  granted("Resource")
+  bounded("PermissionBoundary")
+  bounded("Session")
+}
+permit if {
+  controlled
+  not cross_account
+  identity_permit
+}
+permit if {
+  controlled
+  not cross_account
+  resource_permit
+}
+permit if {
+  controlled
+  cross_account
+  granted("Resource")
+  identity_permit
+}
+permit if {
+  controlled
+  data.regoer.prelude.principal_type != "AWS"
+  named("Resource")
+}
+deny if { count(denied) > 0 }
 allow if {
   permit
   not deny
-}"#;
+}
+decision := {"outcome": "ExplicitDeny", "statements": denied} if { deny } else := {"outcome": "Allow", "statements": allowed} if { permit } else := {"outcome": "ImplicitDeny", "statements": set()}
+applicable(policy) := true if { applies(policy) } else := false
+explanation := {"decision": decision, "policies": [explained |
+  some policy in data.regoer.policies
+  explained := {"policy": policy.policy, "type": policy.type, "applies": applicable(policy), "statements": object.get(policy, "explanation", [])}
+]}
+"#;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
   #[error("generic error: {0}")]
   GenericError(String),
 
-  #[error("unsupported principal type, only 'AWS' is supported")]
+  #[error("unsupported policy version {0}")]
+  UnsupportedVersion(String),
+  #[error("unsupported principal type")]
   UnsupportedPrincipalType,
   #[error("unsupported wildcard")]
   UnsupportedWildcard,
@@ -43,6 +262,34 @@ pub enum Error {
   #[error("invalid string interpolation: {0}")]
   InvalidStringInterpolation(#[from] SubstitutionError),
 
+  #[error("unknown organization node '{0}'")]
+  UnknownOrganizationNode(String),
+  #[error("duplicate organization node '{0}'")]
+  DuplicateOrganizationNode(String),
+
+  #[error("invalid resource scheme '{0}'")]
+  InvalidResourceScheme(String),
+
+  #[error("invalid role ARN '{0}'")]
+  InvalidRoleArn(String),
+  #[error("role assumption denied: {:?}", .0.outcome)]
+  AssumeRoleDenied(Decision),
+  #[error("transitive session tag '{0}' cannot be overridden")]
+  InvalidSessionTag(String),
+  #[error("source identity '{0}' differs from the one of the role chain")]
+  InvalidSourceIdentity(String),
+
+  #[error("input key '{0}' is provided more than once with different cases")]
+  DuplicateInputKey(String),
+
+  #[error("{0}: {1}")]
+  RegoError(SourceLocation, String),
+
+  #[error("invalid JSON: {1}\n{0}")]
+  SyntaxError(Box<ErrorLocation>, String),
+  #[error("{1}\n{0}")]
+  Located(Box<ErrorLocation>, Box<Error>),
+
   #[error("json error: {0}")]
   JsonError(#[from] serde_json::Error),
   #[error("I/O error: {0:?}")]
@@ -51,106 +298,432 @@ pub enum Error {
   FmtError(#[from] std::fmt::Error),
 }
 
+impl Error {
+  /// Error without its location, if any.
+  pub fn inner(&self) -> &Error {
+    match self {
+      Error::Located(_, err) => err.inner(),
+      err => err,
+    }
+  }
+
+  /// Location of the error in the policy document, if known.
+  pub fn location(&self) -> Option<&ErrorLocation> {
+    match self {
+      Error::Located(location, _) | Error::SyntaxError(location, _) => Some(location),
+      _ => None,
+    }
+  }
+
+  /// Attaches a location to the error, keeping the most precise one.
+  pub(crate) fn at(self, location: ErrorLocation) -> Error {
+    match self {
+      err @ (Error::Located(..) | Error::SyntaxError(..)) => err,
+      err => Error::Located(Box::new(location), Box::new(err)),
+    }
+  }
+
+  /// Resolves the line and column of the location against the document.
+  pub(crate) fn resolve(self, document: &str) -> Error {
+    match self {
+      Error::Located(location, err) if location.line.is_none() => Error::Located(Box::new(location.resolve(document)), err),
+      err => err,
+    }
+  }
+}
+
+/// Type of an IAM policy, driving how it takes part in decisions.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PolicyType {
+  /// Policy attached to an identity, granting it permissions.
+  #[default]
+  Identity,
+  /// Policy attached to a resource, granting permissions to principals on it.
+  Resource,
+  /// Maximum permissions an identity-based policy can grant.
+  PermissionBoundary,
+  /// Maximum permissions identity-based policies can grant to a session.
+  Session,
+  /// Maximum permissions available to principals of an account.
+  ServiceControl,
+}
+
 /// Internal AST for an IAM policy.
 #[derive(Clone, Debug)]
-pub struct Policy(pub(crate) Vec<Expr>);
+pub struct Policy {
+  pub(crate) id: usize,
+  pub(crate) name: Option<String>,
+  pub(crate) kind: PolicyType,
+  pub(crate) node: Option<String>,
+  /// Role a trust policy is attached to, the only resource it applies to.
+  pub(crate) role: Option<String>,
+  pub(crate) statements: Vec<Expr>,
+}
 
 impl Policy {
-  /// Serialize the parsed policy to Rego
+  /// Name of the Rego package this policy is emitted into.
+  pub fn package(&self) -> String {
+    format!("regoer.policies.p{}", self.id)
+  }
+
+  /// `Id` element of the policy document, if any.
+  pub fn name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  /// Serialize the parsed policy to a Rego module
+  ///
+  /// The module relies on helper functions from the shared `regoer.prelude`
+  /// package, which is added once by [`Regoer::compile()`](crate::Regoer::compile()).
   pub fn serialize(&self) -> Result<String, Error> {
     let mut buf = String::with_capacity(1024);
 
-    writeln!(buf, "{}", BASE)?;
+    match &self.name {
+      Some(name) => writeln!(buf, "# policy {} ({})", self.id, quote(name))?,
+      None => writeln!(buf, "# policy {}", self.id)?,
+    }
+
+    writeln!(buf, "package {}", self.package())?;
+    writeln!(buf, "policy := {}", self.id)?;
+    writeln!(buf, "type := \"{:?}\"", self.kind)?;
+
+    if let Some(node) = &self.node {
+      writeln!(buf, "node := {}", quote(node))?;
+    }
+
+    if let Some(role) = &self.role {
+      writeln!(buf, "role := {}", quote(role))?;
+    }
 
-    for statement in &self.0 {
+    for statement in &self.statements {
       statement.repr(&mut buf)?;
     }
 
     Ok(buf)
   }
+
+  /// Maps the lines of the serialized module back to the policy document.
+  ///
+  /// Rules are annotated with the JSON pointer of the element they were
+  /// generated from, so that engine errors, which refer to lines of the Rego
+  /// module, can be reported against the original statement.
+  pub fn source_map(&self) -> Result<SourceMap, Error> {
+    Ok(SourceMap::new(self, &self.serialize()?))
+  }
 }
 
 pub fn parse_iam_policy<R>(reader: R) -> Result<Policy, Error>
 where
   R: io::Read,
 {
-  let statements = aws_iam::io::read_from_reader(reader).map_err(Error::PolicyError)?;
+  let document = read_document(reader)?;
+
+  parse_document(&parse_json(&document)?).map_err(|mut errors| errors.remove(0).resolve(&document))
+}
+
+/// Parses the trust policy of a role, whose statements apply to the role when
+/// they have no `Resource` element.
+pub(crate) fn parse_trust_policy<R>(reader: R, role: &str) -> Result<Policy, Error>
+where
+  R: io::Read,
+{
+  let document = read_document(reader)?;
+  let mut json = parse_json(&document)?;
+
+  let statements = match json.get_mut("Statement") {
+    Some(serde_json::Value::Array(statements)) => statements.iter_mut().collect(),
+    Some(statement) => vec![statement],
+    None => vec![],
+  };
+
+  for statement in statements {
+    if let Some(statement) = statement.as_object_mut()
+      && !statement.contains_key("Resource")
+      && !statement.contains_key("NotResource")
+    {
+      statement.insert("Resource".into(), role.into());
+    }
+  }
+
+  let mut policy = parse_document(&json).map_err(|mut errors| errors.remove(0).resolve(&document))?;
+  policy.role = Some(role.to_string());
+
+  Ok(policy)
+}
+
+/// Reads the source of a policy document.
+pub(crate) fn read_document<R>(mut reader: R) -> Result<String, Error>
+where
+  R: io::Read,
+{
+  let mut document = String::new();
+  reader.read_to_string(&mut document).map_err(|err| Error::PolicyError(aws_iam::io::Error::ReadingFile(err)))?;
+
+  Ok(document)
+}
+
+/// Parses the JSON of a document, locating syntax errors.
+pub(crate) fn parse_json(document: &str) -> Result<serde_json::Value, Error> {
+  serde_json::from_str(document).map_err(|err| {
+    let message = err.to_string();
+    let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+
+    Error::SyntaxError(Box::new(ErrorLocation::position(document, err.line(), err.column())), message.to_string())
+  })
+}
+
+/// Parses a policy document, reporting the errors of every statement.
+///
+/// Errors are reported in document order, and located by their JSON pointer
+/// only: their line and column are resolved by the caller against the
+/// source, with [`Error::resolve()`].
+pub(crate) fn parse_document(document: &serde_json::Value) -> Result<Policy, Vec<Error>> {
+  let (name, statements) = read_statements(document)?;
+
   let mut out = vec![];
+  let mut errors = vec![];
+
+  for statement in statements {
+    match statement.and_then(transpile) {
+      Ok(expr) => out.push(expr),
+      Err(errs) => errors.extend(errs),
+    }
+  }
+
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+
+  Ok(Policy {
+    id: 0,
+    name,
+    kind: PolicyType::default(),
+    node: None,
+    role: None,
+    statements: out,
+  })
+}
+
+/// Statements of a document, each either read or failing with its errors.
+pub(crate) type Statements = Vec<Result<Statement, Vec<Error>>>;
+
+/// Reads the `Id` and the statements of a policy document, without transpiling them.
+pub(crate) fn read_statements(document: &serde_json::Value) -> Result<(Option<String>, Statements), Vec<Error>> {
+  if let Some(version) = document.get("Version")
+    && !matches!(version.as_str(), Some("2012-10-17" | "2008-10-17"))
+  {
+    return Err(vec![Error::UnsupportedVersion(version.to_string()).at(ErrorLocation::pointer("/Version"))]);
+  }
+
+  let policy = AwsPolicy::deserialize(document).map_err(|err| match invalid_statements(document) {
+    errors if errors.is_empty() => vec![deserializing(err)],
+    errors => errors,
+  })?;
+
+  // Documents without a `Version` element default to the legacy language version.
+  let version = match policy.version {
+    Some(AwsVersion::V2012) => Version::V2012,
+    Some(AwsVersion::V2008) | None => Version::V2008,
+  };
+
+  let (statements, single) = match policy.statement {
+    OneOrAll::One(one) => (vec![one], true),
+    OneOrAll::All(list) => (list, false),
+  };
+
+  let statements = statements
+    .into_iter()
+    .enumerate()
+    .map(|(index, statement)| {
+      let pointer = match single {
+        true => "/Statement".to_string(),
+        false => source::pointer("/Statement", &index.to_string()),
+      };
+
+      let raw = document.pointer(&pointer).unwrap_or(&serde_json::Value::Null);
+
+      parse_statement(version, index, pointer, statement, raw)
+    })
+    .collect();
+
+  Ok((policy.id, statements))
+}
+
+fn deserializing(err: serde_json::Error) -> Error {
+  Error::PolicyError(aws_iam::io::Error::DeserializingJson(err.to_string()))
+}
+
+/// Transpiles a statement to the rule matching requests against it.
+fn transpile(statement: Statement) -> Result<Expr, Vec<Error>> {
+  let location = ErrorLocation::statement(statement.index, statement.sid.as_deref(), statement.pointer.clone());
+
+  statement.generate().map_err(|err| vec![err.at(location)])
+}
+
+fn parse_statement(version: Version, index: usize, pointer: String, statement: AwsStatement, raw: &serde_json::Value) -> Result<Statement, Vec<Error>> {
+  let at = |element: &str| ErrorLocation::statement(index, statement.sid.as_deref(), source::pointer(&pointer, element));
+
+  let effect = match statement.effect {
+    AwsEffect::Allow => Effect::Allow,
+    AwsEffect::Deny => Effect::Deny,
+  };
+
+  let principals = match statement.principal {
+    None => Ok(wildcard_principal(raw)),
+    Some(Principal::Principal(p)) => parse_principals(p).map(|p| Some(p.into())).map_err(|err| err.at(at("Principal"))),
+    Some(Principal::NotPrincipal(p)) => parse_principals(p).map(|p| Some(Scope::Not(p))).map_err(|err| err.at(at("NotPrincipal"))),
+  };
+
+  let actions = match statement.action {
+    Action::Action(a) => parse_ids(a).map(Scope::Id).map_err(|err| err.at(at("Action"))),
+    Action::NotAction(a) => parse_ids(a).map(Scope::Not).map_err(|err| err.at(at("NotAction"))),
+  };
 
-  for statement in statements.statement.all().unwrap_or_default() {
-    let effect = match statement.effect {
-      AwsEffect::Allow => Effect::Allow,
-      AwsEffect::Deny => Effect::Deny,
-    };
-
-    let principals = match statement.principal {
-      None => Value::One("*".into()).into(),
-      Some(p) => match p {
-        Principal::Principal(p) => match p.get(&aws_iam::model::PrincipalType::AWS).unwrap() {
-          OneOrAny::AnyOf(list) => Value::Many(list.clone()).into(),
-          OneOrAny::One(one) => Value::One(one.to_string()).into(),
-          OneOrAny::Any => Err(Error::UnsupportedPrincipalType)?,
-        },
-
-        Principal::NotPrincipal(_) => Err(Error::UnsupportedNegation)?,
-      },
-    };
-
-    let actions = match statement.action {
-      Action::Action(a) => match a {
-        OneOrAny::AnyOf(list) => Value::Many(list.iter().map(QString::to_string).collect()).into(),
-        OneOrAny::One(one) => Value::One(one.to_string()).into(),
-        OneOrAny::Any => Err(Error::UnsupportedWildcard)?,
-      },
-
-      Action::NotAction(a) => match a {
-        OneOrAny::AnyOf(list) => Scope::Not(Value::Many(list.iter().map(QString::to_string).collect())),
-        OneOrAny::One(one) => Scope::Not(Value::One(one.to_string())),
-        OneOrAny::Any => Err(Error::UnsupportedWildcard)?,
-      },
-    };
-
-    let resources = match statement.resource {
-      Resource::Resource(r) => match r {
-        OneOrAny::AnyOf(list) => Value::Many(list).into(),
-        OneOrAny::One(one) => Value::One(one).into(),
-        OneOrAny::Any => Err(Error::UnsupportedWildcard)?,
-      },
-
-      Resource::NotResource(a) => match a {
-        OneOrAny::AnyOf(list) => Scope::Not(Value::Many(list)),
-        OneOrAny::One(one) => Scope::Not(Value::One(one)),
-        OneOrAny::Any => Err(Error::UnsupportedWildcard)?,
-      },
-    };
-
-    let mut conditions = vec![];
-
-    for (operator, vars) in statement.condition.unwrap_or_default() {
-      let mut attributes = vec![];
-
-      for (attribute, condition) in vars {
-        match condition {
-          OneOrAll::All(list) => attributes.push((attribute, Value::Many(list.into_iter().map(Into::into).collect()))),
-          OneOrAll::One(one) => {
-            attributes.push((attribute, Value::One(one.into())));
-          }
+  let resources = match statement.resource {
+    Resource::Resource(r) => parse_ids(r).map(Scope::Id).map_err(|err| err.at(at("Resource"))),
+    Resource::NotResource(r) => parse_ids(r).map(Scope::Not).map_err(|err| err.at(at("NotResource"))),
+  };
+
+  let mut conditions = vec![];
+
+  for (operator, vars) in statement.condition.unwrap_or_default() {
+    let mut attributes = vec![];
+
+    for (attribute, condition) in vars {
+      match condition {
+        OneOrAll::All(list) => attributes.push((attribute, Value::Many(list.into_iter().map(Into::into).collect()))),
+        OneOrAll::One(one) => {
+          attributes.push((attribute, Value::One(one.into())));
         }
       }
+    }
+
+    attributes.sort_by_key(|(attribute, _)| attribute.to_string());
+    conditions.push((operator, attributes));
+  }
+
+  // Condition blocks are unordered, sort them for the output to be stable.
+  conditions.sort_by_key(|(operator, _)| operator_name(operator));
+
+  let mut errors = vec![];
+
+  let scopes = match (principals, actions, resources) {
+    (Ok(principals), Ok(actions), Ok(resources)) => Some((principals, actions, resources)),
+    (principals, actions, resources) => {
+      errors.extend([principals.err(), actions.err(), resources.err()].into_iter().flatten());
+      None
+    }
+  };
+
+  // Conditions are transpiled when serializing the policy, check them now for
+  // their errors to be located in the document.
+  for (operator, pairs) in &conditions {
+    for pair @ (key, _) in pairs {
+      if let Err(err) = build_condition(version, operator, std::slice::from_ref(pair)) {
+        let location = ErrorLocation::statement(index, statement.sid.as_deref(), source::condition_pointer(&pointer, operator, key));
+
+        errors.push(err.at(location.condition(operator_name(operator), key.to_string())));
+      }
+    }
+  }
+
+  let Some((principals, actions, resources)) = scopes else {
+    return Err(errors);
+  };
+
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+
+  Ok(Statement {
+    version,
+    index,
+    pointer,
+    sid: statement.sid,
+    effect,
+    principals,
+    actions,
+    resources,
+    conditions,
+  })
+}
 
-      conditions.push((operator, attributes));
+/// `"Principal": "*"` or `"NotPrincipal": "*"`, which are deserialized as an absent element.
+fn wildcard_principal(statement: &serde_json::Value) -> Option<Scope<StatementPrincipal>> {
+  let everyone = || Value::One(StatementPrincipal::new(PrincipalType::Everyone, "*"));
+
+  match (statement.get("Principal"), statement.get("NotPrincipal")) {
+    (Some(principal), _) if principal == "*" => Some(Scope::Id(everyone())),
+    (_, Some(principal)) if principal == "*" => Some(Scope::Not(everyone())),
+    _ => None,
+  }
+}
+
+/// Errors of the statements that cannot be deserialized.
+///
+/// Statements are made of untagged enums, for which `serde_json` reports the
+/// end of the document, so they are checked one by one. The other statements
+/// are parsed as well, for their errors to be reported in document order.
+fn invalid_statements(document: &serde_json::Value) -> Vec<Error> {
+  let version = match document.get("Version").and_then(serde_json::Value::as_str) {
+    Some("2012-10-17") => Version::V2012,
+    _ => Version::V2008,
+  };
+
+  let statements = match document.get("Statement") {
+    Some(serde_json::Value::Array(list)) => list
+      .iter()
+      .enumerate()
+      .map(|(index, statement)| (index, statement, source::pointer("/Statement", &index.to_string())))
+      .collect(),
+    Some(statement) => vec![(0, statement, "/Statement".to_string())],
+    None => vec![],
+  };
+
+  let mut errors = vec![];
+  let mut invalid = false;
+
+  for (index, statement, pointer) in statements {
+    match AwsStatement::deserialize(statement) {
+      Ok(parsed) => errors.extend(parse_statement(version, index, pointer, parsed, statement).and_then(transpile).err().unwrap_or_default()),
+      Err(err) => {
+        let location = ErrorLocation::statement(index, statement.get("Sid").and_then(serde_json::Value::as_str), pointer);
+
+        invalid = true;
+        errors.push(deserializing(err).at(location));
+      }
     }
+  }
 
-    let s = Statement {
-      effect,
-      principals,
-      actions,
-      resources,
-      conditions,
-    };
+  match invalid {
+    true => errors,
+    false => vec![],
+  }
+}
+
+fn parse_ids<T: ToString>(ids: OneOrAny<T>) -> Result<Value<String>, Error> {
+  match ids {
+    OneOrAny::AnyOf(list) => Ok(Value::Many(list.iter().map(ToString::to_string).collect())),
+    OneOrAny::One(one) => Ok(Value::One(one.to_string())),
+    OneOrAny::Any => Err(Error::UnsupportedWildcard),
+  }
+}
 
-    out.push(s.generate()?);
+fn parse_principals(principals: HashMap<PrincipalType, OneOrAny>) -> Result<Value<StatementPrincipal>, Error> {
+  let mut out = vec![];
+
+  for (kind, ids) in principals {
+    match ids {
+      OneOrAny::AnyOf(list) => out.extend(list.into_iter().map(|id| StatementPrincipal::new(kind.clone(), id))),
+      OneOrAny::One(one) => out.push(StatementPrincipal::new(kind, one)),
+      OneOrAny::Any => Err(Error::UnsupportedPrincipalType)?,
+    }
   }
 
-  Ok(Policy(out))
+  out.sort_by(|lhs, rhs| (lhs.kind(), &lhs.id).cmp(&(rhs.kind(), &rhs.id)));
+
+  match out.len() {
+    1 => Ok(Value::One(out.remove(0))),
+    _ => Ok(Value::Many(out)),
+  }
 }
@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+use crate::parser::Error;
+
+/// Shape of colon-separated resource identifiers.
+///
+/// Schemes drive how resources, `Arn*` conditions and accounts are matched
+/// and extracted: identifiers are split on `:` into a fixed number of
+/// segments, the last one absorbing any extra colon, and wildcards never
+/// cross segments.
+///
+/// Principal matching rules, such as account roots and role sessions, and
+/// role assumption only recognize IAM and STS ARNs, whatever the registered
+/// schemes.
+///
+/// Identifiers are matched to a scheme through their first segment. By
+/// default, only the `arn` scheme is known, with its partition left
+/// free-form so that identifiers such as `arn:acme:storage::tenant:bucket/key`
+/// are supported.
+///
+/// ## Example
+///
+/// ```rust
+/// # use regoer::{Regoer, ResourceScheme};
+/// #
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut regoer = Regoer::default();
+///
+/// // urn:acme:<tenant>:<service>:<resource>
+/// regoer.add_resource_scheme(ResourceScheme::new("urn", 5)?.account(2)?);
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ResourceScheme {
+  #[serde(skip)]
+  pub(crate) prefix: String,
+  segments: usize,
+  account: Option<usize>,
+}
+
+impl ResourceScheme {
+  /// Scheme for identifiers starting with `prefix` and made of `segments` segments.
+  pub fn new(prefix: impl AsRef<str>, segments: usize) -> Result<ResourceScheme, Error> {
+    let prefix = prefix.as_ref();
+
+    if prefix.is_empty() || prefix.contains([':', '*', '?']) || segments < 2 {
+      return Err(Error::InvalidResourceScheme(prefix.to_string()));
+    }
+
+    Ok(ResourceScheme {
+      prefix: prefix.to_string(),
+      segments,
+      account: None,
+    })
+  }
+
+  /// AWS ARN scheme, `arn:partition:service:region:account:resource`.
+  pub fn arn() -> ResourceScheme {
+    ResourceScheme {
+      prefix: "arn".into(),
+      segments: 6,
+      account: Some(4),
+    }
+  }
+
+  /// Sets the index of the segment holding the account owning the resource.
+  pub fn account(mut self, index: usize) -> Result<ResourceScheme, Error> {
+    if index == 0 || index >= self.segments {
+      return Err(Error::InvalidResourceScheme(self.prefix));
+    }
+
+    self.account = Some(index);
+
+    Ok(self)
+  }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+
+use aws_iam::model::{ConditionOperator, QString};
+
+use crate::{conditions::operator_name, expression::Expr, parser::Policy};
+
+/// Location of a generated Rego rule in the original IAM document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceLocation {
+  /// Index of the policy, in the order policies were added to [`Regoer`](crate::Regoer).
+  pub policy: usize,
+  /// `Id` of the policy document, if any.
+  pub name: Option<String>,
+  /// Index of the statement within its policy.
+  pub statement: usize,
+  /// `Sid` of the statement, if any.
+  pub sid: Option<String>,
+  /// JSON pointer to the element the rule was generated from, such as
+  /// `/Statement/0/Condition/StringEquals/aws:userid`.
+  pub pointer: String,
+}
+
+impl SourceLocation {
+  /// Element of the statement the rule was generated from, such as
+  /// `Condition.StringEquals.aws:userid`, or `None` for the whole statement.
+  pub fn element(&self) -> Option<String> {
+    let segments: Vec<_> = self.pointer.split('/').skip(2).skip_while(|segment| segment.parse::<usize>().is_ok()).map(unescape).collect();
+
+    match segments.is_empty() {
+      true => None,
+      false => Some(segments.join(".")),
+    }
+  }
+}
+
+impl fmt::Display for SourceLocation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.name {
+      Some(name) => write!(f, "policy {name}")?,
+      None => write!(f, "policy {}", self.policy)?,
+    }
+
+    match &self.sid {
+      Some(sid) => write!(f, ", statement {sid}")?,
+      None => write!(f, ", statement {}", self.statement)?,
+    }
+
+    if let Some(element) = self.element() {
+      write!(f, ", {element}")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Mapping from the lines of a generated Rego module to the IAM document it
+/// was generated from.
+///
+/// Every rule emitted by [`Policy::serialize()`] is preceded by a comment
+/// carrying the JSON pointer to the element it was generated from, which
+/// this map resolves back to a [`SourceLocation`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+  file: String,
+  locations: Vec<(usize, SourceLocation)>,
+}
+
+impl SourceMap {
+  pub(crate) fn new(policy: &Policy, rego: &str) -> SourceMap {
+    let mut locations = vec![];
+
+    for (line, text) in rego.lines().enumerate() {
+      let Some((pointer, statement)) = text.strip_prefix("# ").and_then(|annotation| annotation.split_once(" (statement ")) else {
+        continue;
+      };
+
+      let Some(statement) = statement.split([',', ')']).next().and_then(|index| index.parse().ok()) else {
+        continue;
+      };
+
+      let sid = policy.statements.iter().find_map(|expr| match expr {
+        Expr::Statement(header, _, _) if header.index == statement => Some(header.sid.clone()),
+        _ => None,
+      });
+
+      let location = SourceLocation {
+        policy: policy.id,
+        name: policy.name.clone(),
+        statement,
+        sid: sid.flatten(),
+        pointer: pointer.to_string(),
+      };
+
+      locations.push((line + 1, location));
+    }
+
+    SourceMap {
+      file: format!("{}.rego", policy.package()),
+      locations,
+    }
+  }
+
+  /// Name of the Rego module, as reported by the engine.
+  pub fn file(&self) -> &str {
+    &self.file
+  }
+
+  /// Location of the rule spanning the given line, starting from 1.
+  pub fn locate(&self, line: usize) -> Option<&SourceLocation> {
+    self.locations.iter().take_while(|(start, _)| *start <= line).last().map(|(_, location)| location)
+  }
+
+  /// Location of the rule an engine error points to, from its `<file>:<line>:<column>` reference.
+  pub fn resolve(&self, message: &str) -> Option<&SourceLocation> {
+    let (_, position) = message.split_once(&format!("{}:", self.file))?;
+    let line = position.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()?;
+
+    self.locate(line)
+  }
+}
+
+/// Appends a segment to a JSON pointer, escaping `~` and `/`.
+pub(crate) fn pointer(base: &str, segment: &str) -> String {
+  format!("{base}/{}", segment.replace('~', "~0").replace('/', "~1"))
+}
+
+/// JSON pointer to a condition key of a statement.
+pub(crate) fn condition_pointer(statement: &str, operator: &ConditionOperator, key: &QString) -> String {
+  pointer(&pointer(&pointer(statement, "Condition"), &operator_name(operator)), &key.to_string())
+}
+
+fn unescape(segment: &str) -> String {
+  segment.replace("~1", "/").replace("~0", "~")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{SourceLocation, pointer};
+
+  #[test]
+  fn pointers() {
+    let base = pointer("/Statement", "0");
+
+    assert_eq!(pointer(&pointer(&base, "StringEquals"), "aws:PrincipalTag/team"), "/Statement/0/StringEquals/aws:PrincipalTag~1team");
+    assert_eq!(pointer("", "a~b"), "/a~0b");
+  }
+
+  #[test]
+  fn location_display() {
+    let mut location = SourceLocation {
+      policy: 1,
+      name: None,
+      statement: 2,
+      sid: None,
+      pointer: "/Statement/2/Condition/StringLike/aws:PrincipalTag~1team".into(),
+    };
+
+    assert_eq!(location.to_string(), "policy 1, statement 2, Condition.StringLike.aws:PrincipalTag/team");
+
+    location.name = Some("Storage".into());
+    location.sid = Some("Sid1".into());
+    location.pointer = "/Statement/NotAction".into();
+
+    assert_eq!(location.to_string(), "policy Storage, statement Sid1, NotAction");
+
+    location.pointer = "/Statement/2".into();
+
+    assert_eq!(location.to_string(), "policy Storage, statement Sid1");
+    assert_eq!(location.element(), None);
+  }
+}
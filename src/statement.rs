@@ -2,34 +2,67 @@ use serde::Serialize;
 
 use crate::{
   conditions::{Conditions, IdOperatorFunc, OperatorFunc},
-  expression::Expr,
+  expression::{Expr, Header, Scopes, Str},
   extensions::TryMapExprIter,
   functions::Func,
   parser::Error,
-  values::{Effect, Scope, Value},
+  values::{Effect, Principal, Scope, Value, Version},
 };
 
 #[derive(Copy, Clone, Debug, Serialize)]
 pub enum ScopeType {
-  Principal,
   Action,
   Resource,
 }
 
 impl ScopeType {
-  pub fn input_var(&self) -> &'static str {
+  /// Value of the request the scope is matched against.
+  ///
+  /// Actions are matched case-insensitively, as in AWS, while resources are
+  /// case-sensitive.
+  pub fn input(&self) -> Expr {
     match self {
-      ScopeType::Principal => "input.principal",
-      ScopeType::Action => "input.action",
-      ScopeType::Resource => "input.resource",
+      ScopeType::Action => Func::lower(Expr::var("input.action")),
+      ScopeType::Resource => Expr::var("input.resource"),
+    }
+  }
+
+  /// Scope identifier, as matched against [`ScopeType::input()`].
+  ///
+  /// Templates stay templates, their substituted values being lowercased, so
+  /// that [`Expr::glob_pattern()`] can still escape them.
+  pub fn value<S: AsRef<str>>(&self, version: Version, id: S) -> Result<Expr, Error> {
+    let expr = version.str(id)?;
+
+    Ok(match (self, expr) {
+      (ScopeType::Action, Expr::Str(Str::Plain(id))) => Expr::Str(Str::Plain(id.to_lowercase())),
+      (ScopeType::Action, Expr::Str(Str::Template(template, vars))) => Expr::Str(Str::Template(template.to_lowercase(), vars.into_iter().map(Func::lower).collect())),
+      (ScopeType::Action, expr) => Func::lower(expr),
+      (ScopeType::Resource, expr) => expr,
+    })
+  }
+
+  pub fn is_pattern(&self, id: &str) -> bool {
+    id.contains(['*', '?'])
+  }
+
+  pub fn matches(&self, pattern: Expr, value: Expr) -> Expr {
+    match self {
+      ScopeType::Action => Func::glob(pattern, value),
+      ScopeType::Resource => Func::resource_match(pattern, value),
     }
   }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Statement {
+  pub version: Version,
+  pub index: usize,
+  pub pointer: String,
+  pub sid: Option<String>,
   pub effect: Effect,
-  pub principals: Scope<String>,
+  /// `None` when the statement has no `Principal` element.
+  pub principals: Option<Scope<Principal>>,
   pub actions: Scope<String>,
   pub resources: Scope<String>,
   pub conditions: Conditions,
@@ -37,38 +70,120 @@ pub struct Statement {
 
 impl Statement {
   pub fn generate(self) -> Result<Expr, Error> {
-    let mut exprs = vec![];
+    let mut scopes = Scopes {
+      elements: [
+        self.principals.as_ref().map(|scope| element(scope, "Principal", "NotPrincipal")),
+        Some(element(&self.actions, "Action", "NotAction")),
+        Some(element(&self.resources, "Resource", "NotResource")),
+      ],
+      ..Scopes::default()
+    };
+
+    if let Some(principals) = self.principals {
+      scopes.named = Self::named(self.version, &principals)?;
+      scopes.principal = Self::principals(self.version, principals)?;
+    }
 
-    for (kind, scope) in [(ScopeType::Principal, self.principals), (ScopeType::Action, self.actions), (ScopeType::Resource, self.resources)] {
+    for (kind, scope) in [(ScopeType::Action, self.actions), (ScopeType::Resource, self.resources)] {
       let negated = matches!(scope, Scope::Not(_));
 
-      let (op, id, scopes): (OperatorFunc, IdOperatorFunc, _) = match scope {
-        Scope::Id(scopes) => (&Expr::Eq, &Expr::id, scopes),
-        Scope::Not(scopes) => (&Expr::Ne, &Expr::neg, scopes),
+      let (op, id, ids): (OperatorFunc, IdOperatorFunc, _) = match scope {
+        Scope::Id(ids) => (&Expr::Eq, &Expr::id, ids),
+        Scope::Not(ids) => (&Expr::Ne, &Expr::neg, ids),
       };
 
-      let expr = match scopes {
+      let expr = match ids {
         Value::One(one) if one == "*" => None,
         Value::Many(list) if list.iter().all(|id| id == "*") => None,
 
-        Value::One(one) if one.contains('*') => Some(id(Expr::call("glob.match", vec![Expr::str(one)?, Expr::var("null"), Expr::var(kind.input_var())]))),
-        Value::One(one) => Some(op(Expr::var(kind.input_var()).boxed(), Expr::str(one)?.boxed())),
+        Value::One(one) if kind.is_pattern(&one) => Some(id(kind.matches(kind.value(self.version, one)?, kind.input()))),
+        Value::One(one) => Some(op(kind.input().boxed(), kind.value(self.version, one)?.boxed())),
 
-        Value::Many(list) if list.iter().any(|id| id.contains('*')) => match negated {
-          false => Some(id(Func::glob(Expr::AnyIn(Expr::list(list.map_expr(Expr::str)?).boxed()), Expr::var(kind.input_var())))),
-          true => Some(Expr::every(list.map_expr(Expr::str)?, |e| Ok(id(Func::glob(e, Expr::var(kind.input_var())))))?),
+        Value::Many(list) if list.iter().any(|id| kind.is_pattern(id)) => match negated {
+          false => Some(id(kind.matches(Expr::AnyIn(Expr::list(list.map_expr(|id| kind.value(self.version, id))?).boxed()), kind.input()))),
+          true => Some(Expr::every(list.map_expr(|id| kind.value(self.version, id))?, |e| Ok(id(kind.matches(e, kind.input()))))?),
         },
         Value::Many(list) => match negated {
-          false => Some(op(Expr::AnyIn(Expr::list(list.map_expr(Expr::str)?).boxed()).boxed(), Expr::var(kind.input_var()).boxed())),
-          true => Some(Expr::every(list.map_expr(Expr::str)?, |e| Ok(op(e.boxed(), Expr::var(kind.input_var()).boxed())))?),
+          false => Some(op(Expr::AnyIn(Expr::list(list.map_expr(|id| kind.value(self.version, id))?).boxed()).boxed(), kind.input().boxed())),
+          true => Some(Expr::every(list.map_expr(|id| kind.value(self.version, id))?, |e| Ok(op(e.boxed(), kind.input().boxed())))?),
         },
       };
 
-      if let Some(expr) = expr {
-        exprs.push(expr);
+      match kind {
+        ScopeType::Action => scopes.action = expr,
+        ScopeType::Resource => scopes.resource = expr,
       }
     }
 
-    Ok(Expr::Statement(self.effect, exprs, self.conditions))
+    let header = Header {
+      version: self.version,
+      index: self.index,
+      pointer: self.pointer,
+      sid: self.sid,
+      effect: self.effect,
+    };
+
+    Ok(Expr::Statement(header, Box::new(scopes), self.conditions))
+  }
+
+  fn principals(version: Version, scope: Scope<Principal>) -> Result<Option<Expr>, Error> {
+    let negated = matches!(scope, Scope::Not(_));
+
+    let expr = match (&*scope, negated) {
+      (Value::One(one), false) if one.is_wildcard() => None,
+      (Value::Many(list), false) if list.iter().any(Principal::is_wildcard) => None,
+      (Value::One(one), true) if one.is_wildcard() => Some(Expr::Bool(false)),
+      (Value::Many(list), true) if list.iter().any(Principal::is_wildcard) => Some(Expr::Bool(false)),
+
+      (Value::One(one), false) => Some(Func::principal_match(Expr::str(one.kind())?, version.str(&one.id)?)),
+      (Value::Many(list), false) => Some(Func::principal_in(Self::principal_pairs(version, list)?)),
+
+      (Value::One(one), true) => Some(Expr::neg(Func::principal_excluded(Self::principal_pairs(version, std::slice::from_ref(one))?))),
+      (Value::Many(list), true) => Some(Expr::neg(Func::principal_excluded(Self::principal_pairs(version, list)?))),
+    };
+
+    Ok(expr)
+  }
+
+  /// Whether the principal of the request is named by the statement, rather than matched through its account, role or a wildcard.
+  fn named(version: Version, scope: &Scope<Principal>) -> Result<Option<Expr>, Error> {
+    let expr = match scope {
+      Scope::Id(Value::One(one)) if !one.is_wildcard() => Some(Func::principal_named(Self::principal_pairs(version, std::slice::from_ref(one))?)),
+      Scope::Id(Value::Many(list)) if !list.iter().any(Principal::is_wildcard) => Some(Func::principal_named(Self::principal_pairs(version, list)?)),
+      _ => None,
+    };
+
+    Ok(expr)
+  }
+
+  fn principal_pairs(version: Version, principals: &[Principal]) -> Result<Expr, Error> {
+    Ok(Expr::list(
+      principals.map_expr(|principal| Ok(Expr::list(vec![Expr::str(principal.kind())?, version.str(&principal.id)?])))?,
+    ))
+  }
+}
+
+/// Name of the document element a scope was read from.
+pub(crate) fn element<T>(scope: &Scope<T>, id: &'static str, not: &'static str) -> &'static str {
+  match scope {
+    Scope::Id(_) => id,
+    Scope::Not(_) => not,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ScopeType;
+  use crate::{expression::Repr, values::Version};
+
+  #[test]
+  fn escape_action_templates() {
+    let kind = ScopeType::Action;
+    let pattern = kind.value(Version::V2012, "App:[${aws:username}]*").unwrap();
+
+    assert_eq!(
+      kind.matches(pattern, kind.input()).repr_to_string().unwrap(),
+      r#"glob.match(sprintf("app:\\[%s\\]*", [data.regoer.prelude.glob_escape(lower(input.aws.username))]), null, lower(input.action))"#
+    );
   }
 }
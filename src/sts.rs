@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::parser::Error;
+
+/// STS action used to assume a role.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum AssumeRoleAction {
+  /// `sts:AssumeRole`, performed by an IAM principal or another session.
+  AssumeRole,
+  /// `sts:AssumeRoleWithWebIdentity`, performed by a federated identity.
+  AssumeRoleWithWebIdentity,
+}
+
+impl AssumeRoleAction {
+  fn action(&self) -> &'static str {
+    match self {
+      AssumeRoleAction::AssumeRole => "sts:AssumeRole",
+      AssumeRoleAction::AssumeRoleWithWebIdentity => "sts:AssumeRoleWithWebIdentity",
+    }
+  }
+}
+
+/// Request to assume a role, evaluated against the role's trust policy.
+///
+/// Build it with [`AssumeRole::new()`], [`AssumeRole::with_web_identity()`] or,
+/// to chain roles, [`Session::assume_role()`], then pass it to
+/// [`Evaluator::assume_role()`](crate::Evaluator::assume_role()).
+#[derive(Clone, Debug)]
+pub struct AssumeRole {
+  action: AssumeRoleAction,
+  principal: Value,
+  role: String,
+  session_name: String,
+  source_identity: Option<String>,
+  tags: BTreeMap<String, String>,
+  transitive_tags: BTreeSet<String>,
+  caller: Option<Session>,
+  context: Map<String, Value>,
+}
+
+impl AssumeRole {
+  /// Request for `principal` to assume `role` through `sts:AssumeRole`.
+  ///
+  /// `principal` takes the same shape as in [`Evaluator::evaluate()`](crate::Evaluator::evaluate()).
+  pub fn new(principal: impl Serialize, role: impl AsRef<str>, session_name: impl AsRef<str>) -> Result<AssumeRole, Error> {
+    Ok(AssumeRole {
+      action: AssumeRoleAction::AssumeRole,
+      principal: serde_json::to_value(principal)?,
+      role: role.as_ref().to_string(),
+      session_name: session_name.as_ref().to_string(),
+      source_identity: None,
+      tags: BTreeMap::new(),
+      transitive_tags: BTreeSet::new(),
+      caller: None,
+      context: Map::new(),
+    })
+  }
+
+  /// Request for an identity authenticated by the `provider` identity provider
+  /// to assume `role` through `sts:AssumeRoleWithWebIdentity`.
+  ///
+  /// Claims from the web identity token should be provided through
+  /// [`AssumeRole::context()`](Self::context()).
+  pub fn with_web_identity(provider: impl AsRef<str>, role: impl AsRef<str>, session_name: impl AsRef<str>) -> Result<AssumeRole, Error> {
+    let mut request = AssumeRole::new(json!({ "type": "Federated", "id": provider.as_ref() }), role, session_name)?;
+    request.action = AssumeRoleAction::AssumeRoleWithWebIdentity;
+
+    Ok(request)
+  }
+
+  /// Sets the source identity of the session.
+  pub fn source_identity(mut self, source_identity: impl AsRef<str>) -> Self {
+    self.source_identity = Some(source_identity.as_ref().to_string());
+    self
+  }
+
+  /// Adds a session tag, optionally passed on to sessions chained from this one.
+  pub fn tag(mut self, key: impl AsRef<str>, value: impl AsRef<str>, transitive: bool) -> Self {
+    self.tags.insert(key.as_ref().to_string(), value.as_ref().to_string());
+
+    match transitive {
+      true => self.transitive_tags.insert(key.as_ref().to_string()),
+      false => self.transitive_tags.remove(key.as_ref()),
+    };
+
+    self
+  }
+
+  /// Adds freeform context to the request, merged into the evaluation input.
+  pub fn context(mut self, context: impl Serialize) -> Result<Self, Error> {
+    merge(&mut self.context, serde_json::to_value(context)?)?;
+
+    Ok(self)
+  }
+
+  /// Evaluation input for the trust policy of the role.
+  pub(crate) fn input(&self) -> Result<Value, Error> {
+    let mut input = self.context.clone();
+
+    let mut aws = Map::new();
+    let mut sts = Map::new();
+
+    if !self.tags.is_empty() {
+      aws.insert("RequestTag".into(), json!(self.tags));
+      aws.insert("TagKeys".into(), json!(self.tags.keys().collect::<Vec<_>>()));
+    }
+
+    if !self.transitive_tags.is_empty() {
+      sts.insert("TransitiveTagKeys".into(), json!(self.transitive_tags));
+    }
+
+    if let Some(caller) = &self.caller {
+      aws.extend(caller.context());
+    }
+
+    if let Some(source_identity) = &self.source_identity {
+      sts.insert("SourceIdentity".into(), json!(source_identity));
+    }
+
+    sts.insert("RoleSessionName".into(), json!(self.session_name));
+
+    merge(&mut input, json!({ "aws": aws, "sts": sts }))?;
+
+    input.insert("principal".into(), self.principal.clone());
+    input.insert("action".into(), json!(self.action.action()));
+    input.insert("resource".into(), json!(self.role));
+
+    Ok(Value::Object(input))
+  }
+
+  /// Session obtained if the trust policy allows the request.
+  pub(crate) fn session(&self) -> Result<Session, Error> {
+    let (partition, account, name) = parse_role_arn(&self.role)?;
+
+    let mut tags = BTreeMap::new();
+    let mut transitive_tags = BTreeSet::new();
+    let mut source_identity = self.source_identity.clone();
+
+    if let Some(caller) = &self.caller {
+      for key in &caller.transitive_tags {
+        if self.tags.contains_key(key) {
+          return Err(Error::InvalidSessionTag(key.clone()));
+        }
+      }
+
+      if let Some(inherited) = &caller.source_identity {
+        match &source_identity {
+          Some(requested) if requested != inherited => return Err(Error::InvalidSourceIdentity(requested.clone())),
+          _ => source_identity = Some(inherited.clone()),
+        }
+      }
+
+      for key in &caller.transitive_tags {
+        if let Some(value) = caller.tags.get(key) {
+          tags.insert(key.clone(), value.clone());
+          transitive_tags.insert(key.clone());
+        }
+      }
+    }
+
+    tags.extend(self.tags.clone());
+    transitive_tags.extend(self.transitive_tags.iter().cloned());
+
+    Ok(Session {
+      arn: format!("arn:{partition}:sts::{account}:assumed-role/{name}/{}", self.session_name),
+      role: self.role.clone(),
+      account,
+      name: self.session_name.clone(),
+      source_identity,
+      tags,
+      transitive_tags,
+    })
+  }
+}
+
+/// Temporary session obtained by assuming a role.
+///
+/// It acts as the principal of subsequent requests, through
+/// [`Session::request()`], or can assume another role with
+/// [`Session::assume_role()`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+  /// Assumed-role ARN of the session, such as `arn:aws:sts::123456789012:assumed-role/role/session`.
+  pub arn: String,
+  /// ARN of the assumed role.
+  pub role: String,
+  /// Account owning the role.
+  pub account: String,
+  /// Name of the session.
+  pub name: String,
+  /// Source identity, set once in a role chain and kept by chained sessions.
+  pub source_identity: Option<String>,
+  /// Session tags, including transitive tags inherited from the caller session.
+  pub tags: BTreeMap<String, String>,
+  /// Keys of the tags passed on to chained sessions.
+  pub transitive_tags: BTreeSet<String>,
+}
+
+impl Session {
+  /// Request for this session to assume another role.
+  ///
+  /// Transitive tags and the source identity of this session are passed on
+  /// to the new session, and cannot be overridden.
+  pub fn assume_role(&self, role: impl AsRef<str>, session_name: impl AsRef<str>) -> Result<AssumeRole, Error> {
+    let mut request = AssumeRole::new(&self.arn, role, session_name)?;
+    request.caller = Some(self.clone());
+
+    Ok(request)
+  }
+
+  /// Evaluation input for a request performed by this session.
+  ///
+  /// `context` is merged into the input, and takes the same shape as in
+  /// [`Evaluator::evaluate()`](crate::Evaluator::evaluate()). Context keys
+  /// describing the session, such as `aws:PrincipalTag` or
+  /// `aws:SourceIdentity`, are filled from the session.
+  pub fn request(&self, action: impl AsRef<str>, resource: impl AsRef<str>, context: impl Serialize) -> Result<Value, Error> {
+    let mut input = Map::new();
+
+    merge(&mut input, serde_json::to_value(context)?)?;
+    merge(&mut input, json!({ "aws": self.context() }))?;
+
+    input.insert("principal".into(), json!(self.arn));
+    input.insert("action".into(), json!(action.as_ref()));
+    input.insert("resource".into(), json!(resource.as_ref()));
+
+    Ok(Value::Object(input))
+  }
+
+  /// Global context keys describing the session.
+  fn context(&self) -> Map<String, Value> {
+    let mut aws = Map::new();
+
+    aws.insert("PrincipalArn".into(), json!(self.role));
+    aws.insert("PrincipalAccount".into(), json!(self.account));
+
+    if !self.tags.is_empty() {
+      aws.insert("PrincipalTag".into(), json!(self.tags));
+    }
+
+    if let Some(source_identity) = &self.source_identity {
+      aws.insert("SourceIdentity".into(), json!(source_identity));
+    }
+
+    aws
+  }
+}
+
+/// Splits a role ARN into its partition, account and role name.
+pub(crate) fn parse_role_arn(arn: &str) -> Result<(&str, String, &str), Error> {
+  let invalid = || Error::InvalidRoleArn(arn.to_string());
+
+  match arn.splitn(6, ':').collect::<Vec<_>>()[..] {
+    ["arn", partition, "iam", "", account, resource] if !partition.is_empty() && !account.is_empty() => {
+      let name = resource
+        .strip_prefix("role/")
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(invalid)?;
+
+      Ok((partition, account.to_string(), name))
+    }
+
+    _ => Err(invalid()),
+  }
+}
+
+/// Deep-merges `value` into `target`, `null` being treated as an empty object.
+fn merge(target: &mut Map<String, Value>, value: Value) -> Result<(), Error> {
+  let value = match value {
+    Value::Null => return Ok(()),
+    Value::Object(object) => object,
+    other => return Err(Error::InvalidType("object", other.to_string())),
+  };
+
+  for (key, value) in value {
+    match (target.get_mut(&key), value) {
+      (Some(Value::Object(existing)), Value::Object(object)) => merge(existing, Value::Object(object))?,
+      (_, value) => {
+        target.insert(key, value);
+      }
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::parse_role_arn;
+
+  #[test]
+  fn role_arn() {
+    assert_eq!(parse_role_arn("arn:aws:iam::123456789012:role/admin").unwrap(), ("aws", "123456789012".into(), "admin"));
+    assert_eq!(parse_role_arn("arn:aws-cn:iam::123456789012:role/path/to/ops").unwrap(), ("aws-cn", "123456789012".into(), "ops"));
+    assert!(parse_role_arn("arn:aws:iam::123456789012:user/alice").is_err());
+    assert!(parse_role_arn("arn:aws:iam:::role/admin").is_err());
+    assert!(parse_role_arn("arn:aws:s3:::bucket").is_err());
+    assert!(parse_role_arn("admin").is_err());
+  }
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::{
+  location::ErrorLocation,
+  parser::{Error, parse_document, parse_json},
+  source,
+};
+
+/// Severity of a [`Diagnostic`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Severity {
+  /// The policy cannot be compiled.
+  Error,
+  /// The policy compiles, but probably does not behave as intended.
+  Warning,
+}
+
+/// Issue found when validating a policy document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub message: String,
+  pub location: Option<ErrorLocation>,
+}
+
+impl From<Error> for Diagnostic {
+  fn from(err: Error) -> Diagnostic {
+    let (message, location) = match err {
+      Error::SyntaxError(location, message) => (format!("invalid JSON: {message}"), Some(*location)),
+      Error::Located(location, err) => (err.to_string(), Some(*location)),
+      err => (err.to_string(), None),
+    };
+
+    Diagnostic {
+      severity: Severity::Error,
+      message,
+      location,
+    }
+  }
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.severity {
+      Severity::Error => write!(f, "error: {}", self.message)?,
+      Severity::Warning => write!(f, "warning: {}", self.message)?,
+    }
+
+    if let Some(location) = &self.location {
+      write!(f, "\n{location}")?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Walks every statement and condition of a document, collecting all errors and warnings.
+pub(crate) fn validate(source: &str) -> Vec<Diagnostic> {
+  let document = match parse_json(source) {
+    Ok(document) => document,
+    Err(err) => return vec![err.into()],
+  };
+
+  let mut diagnostics: Vec<Diagnostic> = match parse_document(&document) {
+    Ok(_) => vec![],
+    Err(errors) => errors.into_iter().map(|err| err.resolve(source).into()).collect(),
+  };
+
+  diagnostics.extend(warnings(&document, source));
+  diagnostics
+}
+
+/// Constructs that are valid, but probably not meant by the author.
+fn warnings(document: &serde_json::Value, source: &str) -> Vec<Diagnostic> {
+  let Some(object) = document.as_object() else {
+    return vec![];
+  };
+
+  let warning = |message: &str, pointer: &str| Diagnostic {
+    severity: Severity::Warning,
+    message: message.to_string(),
+    location: Some(ErrorLocation::pointer(pointer).resolve(source)),
+  };
+
+  let mut warnings = vec![];
+
+  // Versions which are not strings are already reported as unsupported.
+  let legacy = match object.get("Version") {
+    Some(version) => version.as_str() == Some("2008-10-17"),
+    None => {
+      warnings.push(warning("missing Version element, the policy is evaluated as version 2008-10-17", ""));
+      true
+    }
+  };
+
+  if legacy && let Some(pointer) = object.get("Statement").and_then(|statements| variables(statements, "/Statement")) {
+    warnings.push(warning("policy variables are only interpolated from version 2012-10-17", &pointer));
+  }
+
+  warnings
+}
+
+/// Pointer to the first value holding a policy variable.
+fn variables(value: &serde_json::Value, pointer: &str) -> Option<String> {
+  match value {
+    serde_json::Value::String(s) if s.contains("${") => Some(pointer.to_string()),
+    serde_json::Value::Array(list) => list.iter().enumerate().find_map(|(index, value)| variables(value, &source::pointer(pointer, &index.to_string()))),
+    serde_json::Value::Object(object) => object.iter().find_map(|(key, value)| variables(value, &source::pointer(pointer, key))),
+    _ => None,
+  }
+}
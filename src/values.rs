@@ -1,15 +1,40 @@
 use std::ops::{Deref, DerefMut};
 
-use serde::Serialize;
+use aws_iam::model::PrincipalType;
+use serde::{Deserialize, Serialize};
 
-use crate::parser::Error;
+use crate::{
+  expression::{Expr, Str},
+  parser::Error,
+};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+/// Effect of a statement.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Effect {
   Allow,
   Deny,
 }
 
+/// Version of the policy language a document is written in.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub enum Version {
+  /// Legacy version, in which `${...}` is a literal string.
+  V2008,
+  /// Current version, introducing policy variables.
+  #[default]
+  V2012,
+}
+
+impl Version {
+  /// Builds a string expression, interpolating policy variables if the version supports them.
+  pub fn str<S: AsRef<str>>(&self, str: S) -> Result<Expr, Error> {
+    match self {
+      Version::V2008 => Ok(Expr::Str(Str::literal(str))),
+      Version::V2012 => Expr::str(str),
+    }
+  }
+}
+
 #[derive(Clone, Debug, Serialize, PartialEq, Eq)]
 pub enum Value<T> {
   One(T),
@@ -49,6 +74,13 @@ impl<T> DerefMut for Scope<T> {
 }
 
 impl<T> Value<T> {
+  pub fn as_slice(&self) -> &[T] {
+    match self {
+      Value::One(one) => std::slice::from_ref(one),
+      Value::Many(list) => list,
+    }
+  }
+
   pub fn map<F, O>(&self, mut f: F) -> Result<Value<O>, Error>
   where
     F: FnMut(&T) -> Result<O, Error>,
@@ -65,6 +97,32 @@ impl<T> Value<T> {
   }
 }
 
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Principal {
+  pub kind: PrincipalType,
+  pub id: String,
+}
+
+impl Principal {
+  pub fn new<S: AsRef<str>>(kind: PrincipalType, id: S) -> Principal {
+    Principal { kind, id: id.as_ref().to_string() }
+  }
+
+  pub fn kind(&self) -> &'static str {
+    match self.kind {
+      PrincipalType::Everyone => "*",
+      PrincipalType::AWS => "AWS",
+      PrincipalType::Federated => "Federated",
+      PrincipalType::Service => "Service",
+      PrincipalType::CanonicalUser => "CanonicalUser",
+    }
+  }
+
+  pub fn is_wildcard(&self) -> bool {
+    matches!(self.kind, PrincipalType::Everyone | PrincipalType::AWS) && self.id == "*"
+  }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ConditionValue(pub aws_iam::model::ConditionValue);
 
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 mod helpers;
 
 use serde_json::json;
@@ -249,3 +251,107 @@ fn for_any_values_not_commutative() {
   let req = input(json!({ "TagKeys": [1, 2, 3] }));
   assert_eq!(evaluate(&evaluator, req), true);
 }
+
+// Collection of tests from:
+//  - https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_policies_elements_condition_operators.html#Conditions_IfExists
+
+#[test]
+fn if_exists_string_like() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "ec2:RunInstances",
+          "Resource": "*",
+          "Condition": {"StringLikeIfExists": {"ec2:InstanceType": ["t1.*", "t2.*", "m3.*"]}}
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let req = |ec2: serde_json::Value| json!({ "principal": "anyuser", "action": "ec2:RunInstances", "resource": "*", "ec2": ec2 });
+
+  assert_eq!(evaluate(&evaluator, req(json!({ "InstanceType": "t2.micro" }))), true);
+  assert_eq!(evaluate(&evaluator, req(json!({ "InstanceType": "m5.large" }))), false);
+  assert_eq!(evaluate(&evaluator, req(json!({}))), true);
+}
+
+#[test]
+fn if_exists_bool_deny_without_mfa() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+        },
+        {
+          "Effect": "Deny",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+          "Condition": {"BoolIfExists": {"aws:MultiFactorAuthPresent": "false"}}
+        }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  assert_eq!(evaluate(&evaluator, input(json!({ "MultiFactorAuthPresent": true }))), true);
+  assert_eq!(evaluate(&evaluator, input(json!({ "MultiFactorAuthPresent": false }))), false);
+  assert_eq!(evaluate(&evaluator, input(json!({}))), false);
+}
+
+#[test]
+fn if_exists_all_operators() {
+  let cases = [
+    ("StringEqualsIfExists", json!({ "key": "value" }), json!({ "key": "value" }), json!({ "key": "other" })),
+    ("StringNotEqualsIfExists", json!({ "key": "value" }), json!({ "key": "other" }), json!({ "key": "value" })),
+    ("StringEqualsIgnoreCaseIfExists", json!({ "key": "VALUE" }), json!({ "key": "value" }), json!({ "key": "other" })),
+    ("NumericLessThanIfExists", json!({ "key": 10 }), json!({ "key": 5 }), json!({ "key": 15 })),
+    ("NumericGreaterThanEqualsIfExists", json!({ "key": "10" }), json!({ "key": 10 }), json!({ "key": 9 })),
+    (
+      "DateLessThanIfExists",
+      json!({ "key": "2025-01-01T00:00:00Z" }),
+      json!({ "key": "2024-01-01T00:00:00Z" }),
+      json!({ "key": "2026-01-01T00:00:00Z" }),
+    ),
+    ("IpAddressIfExists", json!({ "key": "10.0.0.0/8" }), json!({ "key": "10.1.2.3" }), json!({ "key": "192.168.1.1" })),
+    ("NotIpAddressIfExists", json!({ "key": "10.0.0.0/8" }), json!({ "key": "192.168.1.1" }), json!({ "key": "10.1.2.3" })),
+    (
+      "ArnLikeIfExists",
+      json!({ "key": "arn:aws:iam::*:role/admin-*" }),
+      json!({ "key": "arn:aws:iam::123456789012:role/admin-ops" }),
+      json!({ "key": "arn:aws:iam::123456789012:role/dev" }),
+    ),
+    (
+      "ArnNotEqualsIfExists",
+      json!({ "key": "arn:aws:iam::123456789012:role/dev" }),
+      json!({ "key": "arn:aws:iam::123456789012:role/ops" }),
+      json!({ "key": "arn:aws:iam::123456789012:role/dev" }),
+    ),
+    ("BoolIfExists", json!({ "key": "true" }), json!({ "key": true }), json!({ "key": false })),
+  ];
+
+  for (operator, condition, matching, non_matching) in cases {
+    let policy = json!({
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "*",
+            "Resource": "*",
+            "Condition": { operator: { "aws:key": condition["key"] } }
+        }]
+    });
+
+    let evaluator = compile_policy(policy);
+
+    assert_eq!(evaluate(&evaluator, input(matching)), true, "{operator} should match");
+    assert_eq!(evaluate(&evaluator, input(non_matching)), false, "{operator} should not match");
+    assert_eq!(evaluate(&evaluator, input(json!({}))), true, "{operator} should match missing key");
+  }
+}
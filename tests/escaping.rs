@@ -0,0 +1,127 @@
+mod helpers;
+
+use regoer::Regoer;
+use serde_json::json;
+
+use crate::helpers::evaluate;
+
+// Characters which are meaningful either in Rego source or in `sprintf` templates.
+const ALPHABET: &[char] = &[
+  'a', 'Z', '0', ' ', '"', '\'', '`', '\\', '\n', '\r', '\t', '\u{0}', '\u{1b}', '\u{7f}', '{', '}', '[', ']', '(', ')', '%', '#', '$', ':', '/', '=', ';', '|', 'é', '\u{2028}',
+];
+
+const INJECTIONS: &[&str] = &[
+  r#"" } allow if { true } x := { ""#,
+  "\"\nallow := true\n#",
+  r#"\" == input.action\n  true\n  \""#,
+  r#"%s%v%d"#,
+  "`; allow := true; `",
+];
+
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  fn string(&mut self) -> String {
+    let len = (self.next() % 24) as usize;
+
+    (0..len).map(|_| ALPHABET[(self.next() % ALPHABET.len() as u64) as usize]).collect()
+  }
+}
+
+fn samples() -> Vec<String> {
+  let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+  INJECTIONS.iter().map(ToString::to_string).chain((0..200).map(|_| rng.string())).filter(|s| !s.contains("${")).collect()
+}
+
+fn compile(policy: serde_json::Value) -> Result<regoer::Evaluator, regoer::Error> {
+  let mut regoer = Regoer::default();
+  regoer.add_policy(policy.to_string().as_bytes())?;
+  regoer.compile()
+}
+
+#[test]
+fn escaped_condition_values() {
+  for value in samples() {
+    let policy = json!({
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "*",
+            "Resource": "*",
+            "Condition": { "StringEquals": { "app:value": value } }
+        }]
+    });
+
+    let evaluator = compile(policy).unwrap_or_else(|err| panic!("{value:?} should compile: {err}"));
+
+    let request = |value: &str| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "*", "app": { "value": value } });
+
+    assert!(evaluate(&evaluator, request(&value)), "{value:?} should match itself");
+    assert!(!evaluate(&evaluator, request(&format!("{value}x"))), "{value:?} should not match another value");
+  }
+}
+
+#[test]
+fn escaped_scopes() {
+  for value in samples().into_iter().filter(|s| !s.contains(['*', '?'])) {
+    let policy = json!({
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": { "AWS": value },
+            "Action": "*",
+            "Resource": value
+        }]
+    });
+
+    let evaluator = compile(policy).unwrap_or_else(|err| panic!("{value:?} should compile: {err}"));
+
+    let request = |principal: &str, resource: &str| json!({ "principal": principal, "action": "s3:GetObject", "resource": resource });
+
+    assert!(evaluate(&evaluator, request(&value, &value)), "{value:?} should match itself");
+    assert!(!evaluate(&evaluator, request(&value, &format!("{value}x"))), "{value:?} should not match another resource");
+    assert!(!evaluate(&evaluator, request(&format!("{value}x"), &value)), "{value:?} should not match another principal");
+  }
+}
+
+#[test]
+fn escaped_templates() {
+  for value in samples() {
+    let default = value.replace('\'', "");
+    let policy = json!({
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "*",
+            "Resource": "*",
+            "Condition": { "StringEquals": { "app:value": format!("{value}${{aws:username}}{value}${{aws:missing, '{default}'}}") } }
+        }]
+    });
+
+    // A `}` in the default value ends the variable early, leaving its quote unterminated.
+    if default.contains('}') {
+      let err = compile(policy).err().unwrap_or_else(|| panic!("{value:?} should not compile"));
+
+      assert!(matches!(err.inner(), regoer::Error::InvalidStringInterpolation(_)), "{value:?} should be rejected: {err}");
+      continue;
+    }
+
+    let evaluator = compile(policy).unwrap_or_else(|err| panic!("{value:?} should compile: {err}"));
+
+    let expected = format!("{value}alice{value}{default}");
+    let request = |value: &str| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "*", "app": { "value": value }, "aws": { "username": "alice" } });
+
+    assert!(evaluate(&evaluator, request(&expected)), "{value:?} should match itself");
+    assert!(!evaluate(&evaluator, request(&format!("{expected}x"))), "{value:?} should not match another value");
+  }
+}
@@ -1,5 +1,8 @@
+#![allow(clippy::bool_assert_comparison)]
+
 mod helpers;
 
+use regoer::{AssumeRole, Effect, FindingCode, FindingSeverity, MatchedStatement, Outcome, PolicyType, Regoer, ResourceScheme, Severity};
 use serde_json::json;
 
 use crate::helpers::{compile_policy, evaluate};
@@ -52,6 +55,48 @@ fn simple_allow_basic_validation() {
   assert_eq!(evaluate(&evaluator, input), false, "Should deny wrong resource");
 }
 
+// Single statement - a Statement element holding an object rather than a list is evaluated
+#[test]
+fn single_statement_object() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": {
+          "Effect": "Allow",
+          "Principal": {"AWS": "testuser"},
+          "Action": "s3:GetObject",
+          "Resource": "arn:aws:s3:::test-bucket/*"
+      }
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |action: &str| json!({ "principal": "testuser", "action": action, "resource": "arn:aws:s3:::test-bucket/key" });
+
+  assert!(evaluate(&evaluator, request("s3:GetObject")));
+  assert!(!evaluate(&evaluator, request("s3:PutObject")));
+}
+
+// Escaping - glob metacharacters other than wildcards are matched literally in patterns
+#[test]
+fn glob_metacharacters_in_patterns() {
+  let evaluator = compile_policy(json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": {"AWS": "testuser"},
+          "Action": "s3:GetObject",
+          "Resource": "arn:aws:s3:::bucket/[abc]/*",
+          "Condition": { "StringLike": { "s3:prefix": "{home,tmp}/*" } }
+      }]
+  }));
+
+  let request = |resource: &str, prefix: &str| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": resource, "s3": { "prefix": prefix } });
+
+  assert!(evaluate(&evaluator, request("arn:aws:s3:::bucket/[abc]/key", "{home,tmp}/alice")));
+  assert!(!evaluate(&evaluator, request("arn:aws:s3:::bucket/a/key", "{home,tmp}/alice")), "Brackets are not a character class");
+  assert!(!evaluate(&evaluator, request("arn:aws:s3:::bucket/[abc]/key", "home/alice")), "Braces are not an alternation");
+}
+
 #[test]
 fn wildcard_with_condition_validation() {
   // Test wildcard action matching combined with multiple conditions
@@ -2578,3 +2623,1932 @@ fn arn_like() {
     ));
   }
 }
+
+// ARN matching - resource IDs may contain colons, and wildcards stay within their segment
+#[test]
+fn arn_segment_matching() {
+  let cases = [
+    (
+      "arn:aws:logs:us-east-1:123456789012:log-group:app:*",
+      "arn:aws:logs:us-east-1:123456789012:log-group:app:log-stream:web",
+      "arn:aws:logs:us-east-1:123456789012:log-group:other:log-stream:web",
+    ),
+    (
+      "arn:aws:states:*:123456789012:execution:machine:*",
+      "arn:aws:states:eu-west-1:123456789012:execution:machine:run",
+      "arn:aws:states:eu-west-1:123456789012:execution:other:run",
+    ),
+    ("arn:aws:s3:::bucket/*", "arn:aws:s3:::bucket/path/to/file.txt", "arn:aws:s3:::other/bucket/file.txt"),
+    (
+      "arn:aws:*:*:123456789012:*",
+      "arn:aws:sqs:eu-west-1:123456789012:queue",
+      "arn:aws:sqs:eu-west-1:210987654321:123456789012:queue",
+    ),
+    ("arn:aws:iam::123456789012:user/???", "arn:aws:iam::123456789012:user/bob", "arn:aws:iam::123456789012:user/alice"),
+  ];
+
+  for (pattern, matching, non_matching) in cases {
+    let request = |resource: &str, arn: &str| json!({ "principal": "alice", "action": "s3:GetObject", "resource": resource, "aws": { "SourceArn": arn } });
+
+    let evaluator = compile_policy(json!({
+        "Version": "2012-10-17",
+        "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "*", "Resource": pattern }]
+    }));
+
+    assert!(evaluate(&evaluator, request(matching, "")), "Resource {pattern} should match {matching}");
+    assert!(!evaluate(&evaluator, request(non_matching, "")), "Resource {pattern} should not match {non_matching}");
+
+    let evaluator = compile_policy(json!({
+        "Version": "2012-10-17",
+        "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "*", "NotResource": pattern }]
+    }));
+
+    assert!(!evaluate(&evaluator, request(matching, "")), "NotResource {pattern} should not match {matching}");
+    assert!(evaluate(&evaluator, request(non_matching, "")), "NotResource {pattern} should match {non_matching}");
+
+    let evaluator = compile_policy(json!({
+        "Version": "2012-10-17",
+        "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "*", "Resource": "*", "Condition": { "ArnLike": { "aws:SourceArn": pattern } } }]
+    }));
+
+    assert!(evaluate(&evaluator, request("*", matching)), "ArnLike {pattern} should match {matching}");
+    assert!(!evaluate(&evaluator, request("*", non_matching)), "ArnLike {pattern} should not match {non_matching}");
+  }
+}
+
+// Typed principals - match on both the principal type and identifier
+#[test]
+fn service_principal() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": {"Service": "lambda.amazonaws.com"},
+          "Action": "sqs:SendMessage",
+          "Resource": "*"
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  assert!(
+    evaluate(
+      &evaluator,
+      json!({
+          "principal": { "type": "Service", "id": "lambda.amazonaws.com" },
+          "action": "sqs:SendMessage",
+          "resource": "arn:aws:sqs:us-east-1:123456789012:queue"
+      })
+    ),
+    "Should allow matching service principal"
+  );
+
+  assert!(
+    !evaluate(
+      &evaluator,
+      json!({
+          "principal": { "type": "AWS", "id": "lambda.amazonaws.com" },
+          "action": "sqs:SendMessage",
+          "resource": "arn:aws:sqs:us-east-1:123456789012:queue"
+      })
+    ),
+    "Should deny same identifier with another principal type"
+  );
+
+  assert!(
+    !evaluate(
+      &evaluator,
+      json!({
+          "principal": "lambda.amazonaws.com",
+          "action": "sqs:SendMessage",
+          "resource": "arn:aws:sqs:us-east-1:123456789012:queue"
+      })
+    ),
+    "Should treat untyped principals as AWS principals"
+  );
+}
+
+#[test]
+fn federated_and_canonical_user_principals() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Effect": "Allow",
+          "Principal": {"Federated": "cognito-identity.amazonaws.com"},
+          "Action": "sts:AssumeRoleWithWebIdentity",
+          "Resource": "*"
+        },
+        {
+          "Effect": "Allow",
+          "Principal": {"CanonicalUser": "79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be"},
+          "Action": "s3:GetObject",
+          "Resource": "*"
+        }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  assert!(
+    evaluate(
+      &evaluator,
+      json!({
+          "principal": { "type": "Federated", "id": "cognito-identity.amazonaws.com" },
+          "action": "sts:AssumeRoleWithWebIdentity",
+          "resource": "arn:aws:iam::123456789012:role/web"
+      })
+    ),
+    "Should allow federated principal"
+  );
+
+  assert!(
+    evaluate(
+      &evaluator,
+      json!({
+          "principal": { "type": "CanonicalUser", "id": "79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be" },
+          "action": "s3:GetObject",
+          "resource": "arn:aws:s3:::bucket/file.txt"
+      })
+    ),
+    "Should allow canonical user principal"
+  );
+
+  assert!(
+    !evaluate(
+      &evaluator,
+      json!({
+          "principal": { "type": "Federated", "id": "cognito-identity.amazonaws.com" },
+          "action": "s3:GetObject",
+          "resource": "arn:aws:s3:::bucket/file.txt"
+      })
+    ),
+    "Should deny federated principal on other statement"
+  );
+}
+
+#[test]
+fn mixed_principal_types() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": {
+              "AWS": ["alice", "bob"],
+              "Service": "lambda.amazonaws.com"
+          },
+          "Action": "s3:GetObject",
+          "Resource": "*"
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  for principal in [json!("alice"), json!({ "type": "AWS", "id": "bob" }), json!({ "type": "Service", "id": "lambda.amazonaws.com" })] {
+    assert!(
+      evaluate(
+        &evaluator,
+        json!({
+            "principal": principal,
+            "action": "s3:GetObject",
+            "resource": "arn:aws:s3:::bucket/file.txt"
+        })
+      ),
+      "Should allow {principal}"
+    );
+  }
+
+  for principal in [json!("eve"), json!({ "type": "Service", "id": "alice" }), json!({ "type": "Federated", "id": "lambda.amazonaws.com" })] {
+    assert!(
+      !evaluate(
+        &evaluator,
+        json!({
+            "principal": principal,
+            "action": "s3:GetObject",
+            "resource": "arn:aws:s3:::bucket/file.txt"
+        })
+      ),
+      "Should deny {principal}"
+    );
+  }
+}
+
+// NotPrincipal - deny everyone except a break-glass role
+#[test]
+fn not_principal_deny() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "s3:*",
+          "Resource": "*"
+        },
+        {
+          "Effect": "Deny",
+          "NotPrincipal": {"AWS": ["arn:aws:iam::123456789012:role/break-glass", "arn:aws:sts::123456789012:assumed-role/break-glass/oncall"]},
+          "Action": "s3:*",
+          "Resource": "*"
+        }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  for principal in ["arn:aws:iam::123456789012:role/break-glass", "arn:aws:sts::123456789012:assumed-role/break-glass/oncall"] {
+    assert!(
+      evaluate(
+        &evaluator,
+        json!({
+            "principal": principal,
+            "action": "s3:GetObject",
+            "resource": "arn:aws:s3:::bucket/file.txt"
+        })
+      ),
+      "Should allow {principal}"
+    );
+  }
+
+  for principal in [
+    json!("arn:aws:iam::123456789012:role/developer"),
+    json!({ "type": "Service", "id": "arn:aws:iam::123456789012:role/break-glass" }),
+  ] {
+    assert!(
+      !evaluate(
+        &evaluator,
+        json!({
+            "principal": principal,
+            "action": "s3:GetObject",
+            "resource": "arn:aws:s3:::bucket/file.txt"
+        })
+      ),
+      "Should deny {principal}"
+    );
+  }
+}
+
+#[test]
+fn not_principal_allow() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "NotPrincipal": {"AWS": "eve"},
+          "Action": "s3:GetObject",
+          "Resource": "*"
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |principal: &str| json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  assert!(evaluate(&evaluator, request("alice")), "Should allow principals not listed");
+  assert!(!evaluate(&evaluator, request("eve")), "Should deny listed principal");
+}
+
+// NotPrincipal - a wildcard excludes every principal
+#[test]
+fn not_principal_wildcard() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": "Allow", "NotPrincipal": "*", "Action": "s3:GetObject", "Resource": "*" }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = json!({ "principal": "alice", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  assert!(!evaluate(&evaluator, request), "Should match no principal");
+}
+
+#[test]
+fn not_principal_account_root() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "s3:*",
+          "Resource": "*"
+        },
+        {
+          "Effect": "Deny",
+          "NotPrincipal": {"AWS": "arn:aws:iam::123456789012:root"},
+          "Action": "s3:*",
+          "Resource": "*"
+        }
+      ]
+  });
+
+  let request = |principal: &str| json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  let evaluator = compile_policy(policy.clone());
+
+  assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:root")), "Should allow account root");
+  assert!(!evaluate(&evaluator, request("arn:aws:iam::123456789012:role/admin")), "Should deny account role by default");
+
+  let mut regoer = Regoer::default();
+  regoer.exempt_account_principals(true);
+  regoer.add_policy(policy.to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:root")), "Should allow account root");
+  assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:role/admin")), "Should allow account role");
+  assert!(evaluate(&evaluator, request("arn:aws:sts::123456789012:assumed-role/admin/session")), "Should allow account session");
+  assert!(!evaluate(&evaluator, request("arn:aws:iam::210987654321:role/admin")), "Should deny other account role");
+  assert!(!evaluate(&evaluator, request("alice")), "Should deny non-ARN principal");
+}
+
+// Principal - account IDs and root ARNs match every principal of the account
+#[test]
+fn principal_account_matching() {
+  let policy = |principal: &str| {
+    json!({
+        "Version": "2012-10-17",
+        "Statement": [{ "Effect": "Allow", "Principal": { "AWS": principal }, "Action": "s3:*", "Resource": "*" }]
+    })
+  };
+
+  let request = |principal: &str| json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  for principal in ["123456789012", "arn:aws:iam::123456789012:root"] {
+    let evaluator = compile_policy(policy(principal));
+
+    assert!(evaluate(&evaluator, request("123456789012")), "{principal} should match the account ID");
+    assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:root")), "{principal} should match the account root");
+    assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:user/alice")), "{principal} should match account users");
+    assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:role/admin")), "{principal} should match account roles");
+    assert!(
+      evaluate(&evaluator, request("arn:aws:sts::123456789012:assumed-role/admin/s1")),
+      "{principal} should match account sessions"
+    );
+    assert!(!evaluate(&evaluator, request("arn:aws:iam::210987654321:user/alice")), "{principal} should not match other accounts");
+    assert!(!evaluate(&evaluator, request("alice")), "{principal} should not match non-ARN principals");
+    assert!(
+      !evaluate(
+        &evaluator,
+        json!({ "principal": { "type": "Service", "id": "123456789012" }, "action": "s3:GetObject", "resource": "*" })
+      ),
+      "{principal} should not match other principal types"
+    );
+  }
+}
+
+// Principal - role ARNs match the sessions of the role
+#[test]
+fn principal_role_session_matching() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": { "AWS": "arn:aws:iam::123456789012:role/teams/admin" },
+          "Action": "s3:*",
+          "Resource": "*"
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+  let request = |principal: &str| json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  assert!(evaluate(&evaluator, request("arn:aws:iam::123456789012:role/teams/admin")), "Should match the role");
+  assert!(evaluate(&evaluator, request("arn:aws:sts::123456789012:assumed-role/admin/alice")), "Should match role sessions");
+  assert!(!evaluate(&evaluator, request("arn:aws:sts::123456789012:assumed-role/developer/alice")), "Should not match other roles");
+  assert!(!evaluate(&evaluator, request("arn:aws:sts::210987654321:assumed-role/admin/alice")), "Should not match other accounts");
+  assert!(!evaluate(&evaluator, request("arn:aws:iam::123456789012:user/admin")), "Should not match users");
+}
+
+// Null - check for the presence of condition keys
+#[test]
+fn null_condition() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*"
+        },
+        {
+          "Effect": "Deny",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+          "Condition": {
+              "Null": {"aws:MultiFactorAuthAge": "true"}
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |aws: serde_json::Value| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt", "aws": aws });
+
+  assert!(evaluate(&evaluator, request(json!({ "MultiFactorAuthAge": 300 }))), "Should allow when key is present");
+  assert!(!evaluate(&evaluator, request(json!({}))), "Should deny when key is absent");
+  assert!(!evaluate(&evaluator, request(json!({ "MultiFactorAuthAge": null }))), "Should deny when key is null");
+}
+
+#[test]
+fn null_condition_multivalued_and_tags() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+          "Condition": {
+              "Null": {
+                  "aws:RequestTag/cost-center": "false",
+                  "aws:TagKeys": false
+              }
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |aws: serde_json::Value| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt", "aws": aws });
+
+  assert!(
+    evaluate(&evaluator, request(json!({ "RequestTag": { "cost-center": "42" }, "TagKeys": ["cost-center"] }))),
+    "Should allow when both keys are present"
+  );
+  assert!(
+    !evaluate(&evaluator, request(json!({ "RequestTag": { "owner": "alice" }, "TagKeys": ["owner"] }))),
+    "Should deny when the tag is absent"
+  );
+  assert!(
+    !evaluate(&evaluator, request(json!({ "RequestTag": { "cost-center": "42" }, "TagKeys": [] }))),
+    "Should deny when the multivalued key is empty"
+  );
+}
+
+// BinaryEquals - compare base64 policy values against base64 or raw bytes
+#[test]
+fn binary_equals() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+          "Condition": {
+              "BinaryEquals": {"app:Signature": ["aGVsbG8=", "/w=="]}
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |signature: serde_json::Value| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt", "app": { "Signature": signature } });
+
+  assert!(evaluate(&evaluator, request(json!("aGVsbG8="))), "Should allow matching base64 text");
+  assert!(evaluate(&evaluator, request(json!("aGVsbG8"))), "Should allow unpadded base64 text");
+  assert!(evaluate(&evaluator, request(json!([104, 101, 108, 108, 111]))), "Should allow matching bytes");
+  assert!(evaluate(&evaluator, request(json!([255]))), "Should allow matching single byte");
+  assert!(!evaluate(&evaluator, request(json!("aGVsbA=="))), "Should deny other base64 text");
+  assert!(!evaluate(&evaluator, request(json!([104, 101, 108, 108]))), "Should deny other bytes");
+  assert!(!evaluate(&evaluator, request(json!([]))), "Should deny empty bytes");
+}
+
+#[test]
+fn binary_equals_invalid_base64() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "*",
+          "Condition": {
+              "BinaryEquals": {"app:Signature": "not base64!"}
+          }
+      }]
+  });
+
+  let mut regoer = Regoer::default();
+
+  assert!(regoer.add_policy(policy.to_string().as_bytes()).is_err());
+}
+
+// Numeric operators with decimal policy and context values
+#[test]
+fn numeric_decimal_conditions() {
+  let cases = [
+    ("NumericEquals", json!("10.5"), json!(10.5), json!(10)),
+    ("NumericNotEquals", json!(10.5), json!(10), json!(10.5)),
+    ("NumericLessThan", json!("10.5"), json!(10), json!(10.5)),
+    ("NumericLessThanEquals", json!(10.5), json!(10.5), json!(10.75)),
+    ("NumericGreaterThan", json!(10), json!(10.25), json!(9.99)),
+    ("NumericGreaterThanEquals", json!("0.5"), json!(0.5), json!(0.25)),
+  ];
+
+  for (operator, value, matching, non_matching) in cases {
+    let policy = json!({
+        "Version": "2012-10-17",
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "*",
+            "Resource": "*",
+            "Condition": { operator: { "app:ratio": value } }
+        }]
+    });
+
+    let evaluator = compile_policy(policy);
+
+    let request = |ratio: serde_json::Value| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/file.txt", "app": { "ratio": ratio } });
+
+    assert!(evaluate(&evaluator, request(matching)), "{operator} should match");
+    assert!(!evaluate(&evaluator, request(non_matching)), "{operator} should not match");
+  }
+}
+
+// Context keys and policy variables which are not valid Rego identifiers
+#[test]
+fn special_characters_context_keys() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "s3:PutObject",
+          "Resource": "arn:aws:s3:::bucket/${aws:PrincipalTag/cost-center}/*",
+          "Condition": {
+              "StringEquals": {
+                  "s3:x-amz-acl": "private",
+                  "aws:RequestTag/cost-center": "42"
+              },
+              "NumericLessThan": {
+                  "s3:content-length": 1024
+              }
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |acl: &str, length: u64| {
+    json!({
+        "principal": "testuser",
+        "action": "s3:PutObject",
+        "resource": "arn:aws:s3:::bucket/42/file.txt",
+        "s3": { "x-amz-acl": acl, "content-length": length },
+        "aws": { "RequestTag": { "cost-center": "42" }, "PrincipalTag": { "cost-center": "42" } }
+    })
+  };
+
+  assert!(evaluate(&evaluator, request("private", 512)), "Should allow matching request");
+  assert!(!evaluate(&evaluator, request("public-read", 512)), "Should deny other ACL");
+  assert!(!evaluate(&evaluator, request("private", 2048)), "Should deny large content");
+}
+
+// Multiple policies - each policy is emitted into its own package
+#[test]
+fn multiple_policies() {
+  let allow = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "s3:*",
+          "Resource": "*",
+          "Condition": { "StringEquals": { "aws:userid": "alice" } }
+      }]
+  });
+
+  let other = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "ec2:*",
+          "Resource": "*"
+      }]
+  });
+
+  let deny = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Deny",
+          "Principal": "*",
+          "Action": "s3:DeleteObject",
+          "Resource": "*"
+      }]
+  });
+
+  let mut regoer = Regoer::default();
+
+  for policy in [&allow, &other, &deny] {
+    regoer.add_policy(policy.to_string().as_bytes()).unwrap();
+  }
+
+  let evaluator = regoer.compile().unwrap();
+  let rego = evaluator.to_string();
+
+  assert_eq!(rego.matches("package regoer.prelude").count(), 1);
+  assert_eq!(rego.matches("to_array(x) := x").count(), 1);
+
+  for package in ["regoer.policies.p0", "regoer.policies.p1", "regoer.policies.p2"] {
+    assert_eq!(rego.matches(&format!("package {package}\n")).count(), 1);
+  }
+
+  let request = |action: &str| json!({ "principal": "alice", "action": action, "resource": "arn:aws:s3:::bucket/file.txt", "aws": { "userid": "alice" } });
+
+  assert!(evaluate(&evaluator, request("s3:GetObject")), "Should allow from first policy");
+  assert!(evaluate(&evaluator, request("ec2:RunInstances")), "Should allow from second policy");
+  assert!(!evaluate(&evaluator, request("s3:DeleteObject")), "Should deny from third policy");
+  assert!(!evaluate(&evaluator, request("iam:CreateUser")), "Should deny actions no policy allows");
+}
+
+// Decisions - report the outcome and the statements that caused it
+#[test]
+fn structured_decision() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        {
+          "Sid": "AllowRead",
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "s3:Get*",
+          "Resource": "*"
+        },
+        {
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": ["s3:GetObject", "s3:DeleteObject"],
+          "Resource": "*"
+        },
+        {
+          "Sid": "DenyDelete",
+          "Effect": "Deny",
+          "Principal": "*",
+          "Action": "s3:DeleteObject",
+          "Resource": "*"
+        }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+  let request = |action: &str| json!({ "principal": "alice", "action": action, "resource": "arn:aws:s3:::bucket/file.txt" });
+
+  let decision = evaluator.evaluate(&request("s3:GetObject")).unwrap();
+
+  assert_eq!(decision.outcome, Outcome::Allow);
+  assert_eq!(
+    decision.statements,
+    vec![
+      MatchedStatement {
+        policy: 0,
+        statement: 0,
+        sid: Some("AllowRead".into())
+      },
+      MatchedStatement { policy: 0, statement: 1, sid: None },
+    ]
+  );
+
+  let decision = evaluator.evaluate(&request("s3:DeleteObject")).unwrap();
+
+  assert_eq!(decision.outcome, Outcome::ExplicitDeny);
+  assert_eq!(
+    decision.statements,
+    vec![MatchedStatement {
+      policy: 0,
+      statement: 2,
+      sid: Some("DenyDelete".into())
+    }]
+  );
+  assert!(!decision.is_allowed());
+
+  let decision = evaluator.evaluate(&request("s3:PutObject")).unwrap();
+
+  assert_eq!(decision.outcome, Outcome::ImplicitDeny);
+  assert!(decision.statements.is_empty());
+}
+
+// Decisions - statements are attributed to the policy they come from
+#[test]
+fn structured_decision_multiple_policies() {
+  let allow = json!({
+      "Version": "2012-10-17",
+      "Statement": { "Sid": "Allow", "Effect": "Allow", "Principal": "*", "Action": "*", "Resource": "*" }
+  });
+
+  let deny = json!({
+      "Version": "2012-10-17",
+      "Statement": { "Effect": "Deny", "Principal": "*", "Action": "iam:*", "Resource": "*" }
+  });
+
+  let mut regoer = Regoer::default();
+  regoer.add_policy(allow.to_string().as_bytes()).unwrap();
+  regoer.add_policy(deny.to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+  let request = |action: &str| json!({ "principal": "alice", "action": action, "resource": "*" });
+
+  let decision = evaluator.evaluate(&request("s3:GetObject")).unwrap();
+
+  assert_eq!(decision.outcome, Outcome::Allow);
+  assert_eq!(
+    decision.statements,
+    vec![MatchedStatement {
+      policy: 0,
+      statement: 0,
+      sid: Some("Allow".into())
+    }]
+  );
+
+  let decision = evaluator.evaluate(&request("iam:CreateUser")).unwrap();
+
+  assert_eq!(decision.outcome, Outcome::ExplicitDeny);
+  assert_eq!(decision.statements, vec![MatchedStatement { policy: 1, statement: 0, sid: None }]);
+}
+
+fn statement(effect: &str, action: &str) -> serde_json::Value {
+  json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": effect, "Principal": "*", "Action": action, "Resource": "*" }]
+  })
+}
+
+fn principal_statement(effect: &str, action: &str, principal: &str) -> serde_json::Value {
+  json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": effect, "Principal": { "AWS": principal }, "Action": action, "Resource": "*" }]
+  })
+}
+
+fn compile_typed_policies(policies: &[(PolicyType, serde_json::Value)]) -> regoer::Evaluator {
+  let mut regoer = Regoer::default();
+
+  for (kind, policy) in policies {
+    regoer.add_policy_with_type(*kind, policy.to_string().as_bytes()).unwrap();
+  }
+
+  regoer.compile().unwrap()
+}
+
+fn outcome(evaluator: &regoer::Evaluator, action: &str) -> Outcome {
+  evaluator.evaluate(&json!({ "principal": "alice", "action": action, "resource": "*" })).unwrap().outcome
+}
+
+// Policy types - service control policies restrict identity-based policies
+#[test]
+fn service_control_policies() {
+  let evaluator = compile_typed_policies(&[(PolicyType::Identity, statement("Allow", "*")), (PolicyType::ServiceControl, statement("Allow", "s3:*"))]);
+
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::Allow);
+  assert_eq!(outcome(&evaluator, "ec2:RunInstances"), Outcome::ImplicitDeny);
+
+  let evaluator = compile_typed_policies(&[(PolicyType::ServiceControl, statement("Allow", "*"))]);
+
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::ImplicitDeny, "SCPs do not grant permissions");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Identity, statement("Allow", "*")), (PolicyType::ServiceControl, statement("Deny", "iam:*"))]);
+
+  assert_eq!(outcome(&evaluator, "iam:CreateUser"), Outcome::ExplicitDeny);
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::ImplicitDeny, "SCPs with only denies allow nothing");
+}
+
+// Policy types - permission boundaries and session policies intersect with identity-based policies
+#[test]
+fn permission_boundaries_and_session_policies() {
+  let evaluator = compile_typed_policies(&[
+    (PolicyType::Identity, statement("Allow", "s3:*")),
+    (PolicyType::Identity, statement("Allow", "ec2:*")),
+    (PolicyType::PermissionBoundary, statement("Allow", "s3:*")),
+    (PolicyType::PermissionBoundary, statement("Allow", "iam:*")),
+  ]);
+
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::Allow);
+  assert_eq!(outcome(&evaluator, "ec2:RunInstances"), Outcome::ImplicitDeny, "Outside the boundary");
+  assert_eq!(outcome(&evaluator, "iam:CreateUser"), Outcome::ImplicitDeny, "Boundaries do not grant permissions");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Identity, statement("Allow", "*")), (PolicyType::Session, statement("Allow", "s3:Get*"))]);
+
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::Allow);
+  assert_eq!(outcome(&evaluator, "s3:PutObject"), Outcome::ImplicitDeny, "Outside the session policy");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Identity, statement("Allow", "*")), (PolicyType::Session, statement("Deny", "s3:*"))]);
+
+  assert_eq!(outcome(&evaluator, "s3:GetObject"), Outcome::ExplicitDeny);
+}
+
+fn resource_outcome(evaluator: &regoer::Evaluator, principal: &str, action: &str) -> Outcome {
+  evaluator
+    .evaluate(&json!({ "principal": principal, "action": action, "resource": "arn:aws:sqs:eu-west-1:111111111111:queue" }))
+    .unwrap()
+    .outcome
+}
+
+// Policy types - resource-based policies grant access on their own
+#[test]
+fn resource_policies() {
+  let user = "arn:aws:iam::111111111111:user/alice";
+
+  let evaluator = compile_typed_policies(&[
+    (PolicyType::Resource, principal_statement("Allow", "sqs:SendMessage", user)),
+    (PolicyType::Identity, statement("Allow", "sqs:DeleteMessage")),
+    (PolicyType::PermissionBoundary, statement("Allow", "sqs:ListQueues")),
+  ]);
+
+  assert_eq!(resource_outcome(&evaluator, user, "sqs:SendMessage"), Outcome::Allow, "Resource policy grants on its own");
+  assert_eq!(resource_outcome(&evaluator, user, "sqs:DeleteMessage"), Outcome::ImplicitDeny, "Outside the boundary");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, statement("Allow", "sqs:*")), (PolicyType::ServiceControl, statement("Allow", "sqs:Send*"))]);
+
+  assert_eq!(resource_outcome(&evaluator, user, "sqs:SendMessage"), Outcome::Allow);
+  assert_eq!(resource_outcome(&evaluator, user, "sqs:DeleteMessage"), Outcome::ImplicitDeny, "SCPs apply to resource policies");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, statement("Allow", "*")), (PolicyType::Identity, statement("Deny", "sqs:*"))]);
+
+  assert_eq!(resource_outcome(&evaluator, user, "sqs:SendMessage"), Outcome::ExplicitDeny, "Explicit deny wins");
+  assert_eq!(resource_outcome(&evaluator, user, "ec2:RunInstances"), Outcome::Allow);
+}
+
+// Policy types - boundaries and session policies restrict resource-based grants that do not name the principal
+#[test]
+fn resource_policies_bounded() {
+  let role = "arn:aws:iam::111111111111:role/ops";
+  let session = "arn:aws:sts::111111111111:assumed-role/ops/alice";
+
+  let boundary = (PolicyType::PermissionBoundary, statement("Allow", "sqs:ListQueues"));
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, statement("Allow", "sqs:*")), boundary.clone()]);
+
+  assert_eq!(resource_outcome(&evaluator, role, "sqs:SendMessage"), Outcome::ImplicitDeny, "Outside the boundary");
+  assert_eq!(resource_outcome(&evaluator, role, "sqs:ListQueues"), Outcome::Allow);
+  assert_eq!(
+    resource_outcome(&evaluator, session, "sqs:SendMessage"),
+    Outcome::ImplicitDeny,
+    "Sessions matched by a wildcard are bounded"
+  );
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, principal_statement("Allow", "sqs:*", role)), boundary.clone()]);
+
+  assert_eq!(
+    resource_outcome(&evaluator, session, "sqs:SendMessage"),
+    Outcome::ImplicitDeny,
+    "Sessions matched by their role are bounded"
+  );
+  assert_eq!(resource_outcome(&evaluator, session, "sqs:ListQueues"), Outcome::Allow);
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, principal_statement("Allow", "sqs:*", session)), boundary]);
+
+  assert_eq!(resource_outcome(&evaluator, session, "sqs:SendMessage"), Outcome::Allow, "Named sessions are not bounded");
+
+  let evaluator = compile_typed_policies(&[
+    (PolicyType::Resource, principal_statement("Allow", "sqs:*", session)),
+    (PolicyType::Session, statement("Allow", "sqs:ListQueues")),
+  ]);
+
+  assert_eq!(resource_outcome(&evaluator, session, "sqs:SendMessage"), Outcome::Allow);
+
+  let evaluator = compile_typed_policies(&[
+    (PolicyType::Resource, principal_statement("Allow", "sqs:*", role)),
+    (PolicyType::Session, statement("Allow", "sqs:ListQueues")),
+  ]);
+
+  assert_eq!(resource_outcome(&evaluator, session, "sqs:SendMessage"), Outcome::ImplicitDeny, "Outside the session policy");
+}
+
+fn organization() -> Regoer {
+  let mut regoer = Regoer::default();
+
+  regoer.add_organization_node("r-root", None).unwrap();
+  regoer.add_organization_node("ou-workloads", Some("r-root")).unwrap();
+  regoer.add_organization_node("ou-sandbox", Some("r-root")).unwrap();
+  regoer.add_organization_node("111111111111", Some("ou-workloads")).unwrap();
+  regoer.add_organization_node("222222222222", Some("ou-sandbox")).unwrap();
+
+  regoer.add_policy(statement("Allow", "*").to_string().as_bytes()).unwrap();
+
+  regoer
+}
+
+fn account_outcome(evaluator: &regoer::Evaluator, account: &str, action: &str) -> Outcome {
+  let principal = format!("arn:aws:iam::{account}:user/alice");
+
+  evaluator.evaluate(&json!({ "principal": principal, "action": action, "resource": "*" })).unwrap().outcome
+}
+
+// Organizations - every level from the root to the account must allow the request
+#[test]
+fn service_control_policy_hierarchy() {
+  let mut regoer = organization();
+
+  regoer.add_service_control_policy("r-root", statement("Allow", "*").to_string().as_bytes()).unwrap();
+  regoer.add_service_control_policy("ou-workloads", statement("Allow", "s3:*").to_string().as_bytes()).unwrap();
+  regoer.add_service_control_policy("ou-workloads", statement("Allow", "ec2:*").to_string().as_bytes()).unwrap();
+  regoer.add_service_control_policy("111111111111", statement("Allow", "s3:*").to_string().as_bytes()).unwrap();
+  regoer.add_service_control_policy("ou-sandbox", statement("Allow", "ec2:*").to_string().as_bytes()).unwrap();
+  regoer
+    .add_service_control_policy("ou-sandbox", statement("Deny", "ec2:TerminateInstances").to_string().as_bytes())
+    .unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  assert_eq!(account_outcome(&evaluator, "111111111111", "s3:GetObject"), Outcome::Allow);
+  assert_eq!(account_outcome(&evaluator, "111111111111", "ec2:RunInstances"), Outcome::ImplicitDeny, "Denied at the account level");
+  assert_eq!(
+    account_outcome(&evaluator, "111111111111", "ec2:TerminateInstances"),
+    Outcome::ImplicitDeny,
+    "Sandbox denies do not apply"
+  );
+  assert_eq!(account_outcome(&evaluator, "222222222222", "ec2:RunInstances"), Outcome::Allow);
+  assert_eq!(account_outcome(&evaluator, "222222222222", "s3:GetObject"), Outcome::ImplicitDeny, "Denied at the OU level");
+  assert_eq!(account_outcome(&evaluator, "222222222222", "ec2:TerminateInstances"), Outcome::ExplicitDeny);
+  assert_eq!(
+    account_outcome(&evaluator, "333333333333", "iam:CreateUser"),
+    Outcome::ImplicitDeny,
+    "Accounts outside the organization"
+  );
+
+  let request = json!({ "principal": "alice", "action": "ec2:RunInstances", "resource": "*", "aws": { "PrincipalAccount": "111111111111" } });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny, "Account from the context");
+}
+
+// Organizations - requests whose account is unknown to the tree are denied
+#[test]
+fn organization_unknown_accounts() {
+  let mut regoer = organization();
+
+  regoer.add_service_control_policy("r-root", statement("Allow", "*").to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  assert_eq!(account_outcome(&evaluator, "111111111111", "s3:GetObject"), Outcome::Allow);
+  assert_eq!(account_outcome(&evaluator, "333333333333", "s3:GetObject"), Outcome::ImplicitDeny, "Account outside the tree");
+
+  let request = json!({ "principal": "alice", "action": "s3:GetObject", "resource": "*" });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny, "Unresolved account");
+
+  let mut regoer = organization();
+
+  regoer.add_service_control_policy("ou-workloads", statement("Allow", "s3:*").to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny, "Node policies are not skipped");
+}
+
+// Organizations - the root restricts every account
+#[test]
+fn service_control_policy_root() {
+  let mut regoer = organization();
+
+  regoer.add_service_control_policy("r-root", statement("Allow", "s3:*").to_string().as_bytes()).unwrap();
+  regoer.add_service_control_policy("ou-workloads", statement("Allow", "*").to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  assert_eq!(account_outcome(&evaluator, "111111111111", "s3:GetObject"), Outcome::Allow);
+  assert_eq!(account_outcome(&evaluator, "111111111111", "ec2:RunInstances"), Outcome::ImplicitDeny);
+  assert_eq!(account_outcome(&evaluator, "222222222222", "ec2:RunInstances"), Outcome::ImplicitDeny);
+}
+
+// Organizations - nodes must be known
+#[test]
+fn organization_unknown_nodes() {
+  let mut regoer = organization();
+
+  assert!(matches!(regoer.add_organization_node("ou-other", Some("ou-missing")), Err(regoer::Error::UnknownOrganizationNode(_))));
+  assert!(matches!(regoer.add_organization_node("ou-sandbox", Some("r-root")), Err(regoer::Error::DuplicateOrganizationNode(_))));
+  assert!(matches!(
+    regoer.add_service_control_policy("ou-missing", statement("Allow", "*").to_string().as_bytes()),
+    Err(regoer::Error::UnknownOrganizationNode(_))
+  ));
+}
+
+// Cross-account - access requires both an identity-based and a resource-based policy
+#[test]
+fn cross_account_access() {
+  let identity = statement("Allow", "sqs:*");
+  let resource = statement("Allow", "sqs:SendMessage");
+
+  let request = |principal: &str, action: &str| json!({ "principal": principal, "action": action, "resource": "arn:aws:sqs:eu-west-1:111111111111:queue" });
+
+  let outcome = |policies: &[(PolicyType, serde_json::Value)], principal: &str, action: &str| compile_typed_policies(policies).evaluate(&request(principal, action)).unwrap().outcome;
+
+  let local = "arn:aws:iam::111111111111:user/alice";
+  let remote = "arn:aws:iam::222222222222:user/bob";
+
+  assert_eq!(outcome(&[(PolicyType::Identity, identity.clone())], local, "sqs:SendMessage"), Outcome::Allow);
+  assert_eq!(outcome(&[(PolicyType::Resource, resource.clone())], local, "sqs:SendMessage"), Outcome::Allow);
+
+  assert_eq!(
+    outcome(&[(PolicyType::Identity, identity.clone())], remote, "sqs:SendMessage"),
+    Outcome::ImplicitDeny,
+    "Missing resource policy"
+  );
+  assert_eq!(
+    outcome(&[(PolicyType::Resource, resource.clone())], remote, "sqs:SendMessage"),
+    Outcome::ImplicitDeny,
+    "Missing identity policy"
+  );
+
+  let both = [(PolicyType::Identity, identity), (PolicyType::Resource, resource)];
+
+  assert_eq!(outcome(&both, remote, "sqs:SendMessage"), Outcome::Allow);
+  assert_eq!(outcome(&both, remote, "sqs:DeleteQueue"), Outcome::ImplicitDeny, "Not allowed by the resource policy");
+}
+
+// Cross-account - accounts can be provided through the context
+#[test]
+fn cross_account_context() {
+  let evaluator = compile_typed_policies(&[(PolicyType::Identity, statement("Allow", "*"))]);
+
+  let request = |principal_account: &str, resource_account: &str| json!({ "principal": "alice", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key", "aws": { "PrincipalAccount": principal_account, "ResourceAccount": resource_account } });
+
+  assert_eq!(evaluator.evaluate(&request("111111111111", "111111111111")).unwrap().outcome, Outcome::Allow);
+  assert_eq!(evaluator.evaluate(&request("111111111111", "222222222222")).unwrap().outcome, Outcome::ImplicitDeny);
+
+  let request = json!({ "principal": "arn:aws:iam::111111111111:user/alice", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key" });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Resources without an account are not cross-account");
+}
+
+// Cross-account - resource-based policies naming a service principal grant access on their own
+#[test]
+fn cross_account_service_principal() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": "Allow", "Principal": { "Service": "sns.amazonaws.com" }, "Action": "sqs:SendMessage", "Resource": "*" }]
+  });
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, policy)]);
+
+  let request = |principal: &str, account: &str| json!({ "principal": { "type": "Service", "id": principal }, "action": "sqs:SendMessage", "resource": "arn:aws:sqs:eu-west-1:111111111111:queue", "aws": { "PrincipalAccount": account } });
+
+  assert_eq!(evaluator.evaluate(&request("sns.amazonaws.com", "111111111111")).unwrap().outcome, Outcome::Allow);
+  assert_eq!(evaluator.evaluate(&request("sns.amazonaws.com", "222222222222")).unwrap().outcome, Outcome::Allow, "Other account");
+  assert_eq!(
+    evaluator.evaluate(&request("events.amazonaws.com", "222222222222")).unwrap().outcome,
+    Outcome::ImplicitDeny,
+    "Other service"
+  );
+
+  let request = json!({ "principal": { "type": "Service", "id": "sns.amazonaws.com" }, "action": "sqs:SendMessage", "resource": "arn:aws:sqs:eu-west-1:111111111111:queue" });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Unresolved principal account");
+}
+
+// Cross-account - resource-based policies grant on their own to resources without an account
+#[test]
+fn cross_account_unresolved_resource() {
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, statement("Allow", "s3:GetObject"))]);
+
+  let principal = "arn:aws:iam::111111111111:user/alice";
+
+  for resource in ["*", "bucket/key", "arn:aws:s3:::bucket/key"] {
+    let request = json!({ "principal": principal, "action": "s3:GetObject", "resource": resource });
+
+    assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Unresolved account for {resource}");
+  }
+
+  let request = json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key", "aws": { "ResourceAccount": "222222222222" } });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny, "Account from the context");
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, statement("Allow", "s3:GetObject")), (PolicyType::Identity, statement("Allow", "s3:*"))]);
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Both grants");
+}
+
+// Cross-account - resource-based policies only affect the resources they match
+#[test]
+fn cross_account_other_resource() {
+  let queue = json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "sqs:*", "Resource": "arn:aws:sqs:eu-west-1:111111111111:queue" }]
+  });
+
+  let evaluator = compile_typed_policies(&[(PolicyType::Resource, queue), (PolicyType::Identity, statement("Allow", "s3:*"))]);
+
+  for principal in ["alice", "arn:aws:iam::111111111111:user/alice"] {
+    let request = json!({ "principal": principal, "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key" });
+
+    assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Identity grant for {principal}");
+  }
+
+  let request = json!({ "principal": "arn:aws:iam::222222222222:user/bob", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key", "aws": { "ResourceAccount": "111111111111" } });
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny, "No resource policy matches the bucket");
+}
+
+fn trust_policy(role: &str, statement: serde_json::Value) -> regoer::Evaluator {
+  let mut regoer = Regoer::default();
+
+  regoer
+    .add_trust_policy(role, json!({ "Version": "2012-10-17", "Statement": [statement] }).to_string().as_bytes())
+    .unwrap();
+
+  regoer.compile().unwrap()
+}
+
+// STS - trust policies drive role assumption and derive a session principal
+#[test]
+fn assume_role() {
+  let evaluator = trust_policy(
+    "arn:aws:iam::111111111111:role/path/ops",
+    json!({
+        "Effect": "Allow",
+        "Principal": { "AWS": "arn:aws:iam::111111111111:user/alice" },
+        "Action": "sts:AssumeRole",
+        "Condition": { "StringEquals": { "aws:RequestTag/team": "ops" } }
+    }),
+  );
+
+  let request = AssumeRole::new("arn:aws:iam::111111111111:user/alice", "arn:aws:iam::111111111111:role/path/ops", "alice").unwrap();
+
+  assert!(matches!(evaluator.assume_role(request.clone()), Err(regoer::Error::AssumeRoleDenied(_))), "Missing session tag");
+
+  let session = evaluator.assume_role(request.tag("team", "ops", false).source_identity("alice@example.com")).unwrap();
+
+  assert_eq!(session.arn, "arn:aws:sts::111111111111:assumed-role/ops/alice");
+  assert_eq!(session.account, "111111111111");
+  assert_eq!(session.source_identity.as_deref(), Some("alice@example.com"));
+
+  let request = AssumeRole::new("arn:aws:iam::111111111111:user/bob", "arn:aws:iam::111111111111:role/path/ops", "bob").unwrap();
+
+  assert!(evaluator.assume_role(request.tag("team", "ops", false)).is_err(), "Principal not trusted");
+
+  let request = AssumeRole::new("arn:aws:iam::111111111111:user/alice", "arn:aws:iam::111111111111:role/admin", "alice").unwrap();
+
+  assert!(evaluator.assume_role(request.tag("team", "ops", false)).is_err(), "Role without a trust policy");
+
+  let request = AssumeRole::new("arn:aws:iam::111111111111:user/alice", "arn:aws:iam::111111111111:user/ops", "alice").unwrap();
+
+  assert!(matches!(evaluator.assume_role(request.tag("team", "ops", false)), Err(regoer::Error::InvalidRoleArn(_))));
+}
+
+// STS - sessions act as principals carrying their tags and source identity
+#[test]
+fn assume_role_session_requests() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": { "AWS": "arn:aws:sts::111111111111:assumed-role/ops/*" },
+          "Action": "s3:GetObject",
+          "Resource": "*",
+          "Condition": {
+              "StringEquals": { "aws:PrincipalTag/team": "ops", "aws:SourceIdentity": "alice" },
+              "IpAddress": { "aws:SourceIp": "10.0.0.0/8" }
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+  let session = trust_policy("arn:aws:iam::111111111111:role/ops", json!({ "Effect": "Allow", "Principal": "*", "Action": "sts:AssumeRole" }))
+    .assume_role(
+      AssumeRole::new("arn:aws:iam::111111111111:user/alice", "arn:aws:iam::111111111111:role/ops", "s1")
+        .unwrap()
+        .tag("team", "ops", false)
+        .source_identity("alice"),
+    )
+    .unwrap();
+
+  let request = session.request("s3:GetObject", "arn:aws:s3:::bucket/key", json!({ "aws": { "SourceIp": "10.1.2.3" } })).unwrap();
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow);
+
+  let request = session.request("s3:GetObject", "arn:aws:s3:::bucket/key", json!({ "aws": { "SourceIp": "192.168.1.1" } })).unwrap();
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::ImplicitDeny);
+
+  let request = session
+    .request(
+      "s3:GetObject",
+      "arn:aws:s3:::bucket/key",
+      json!({ "aws": { "SourceIp": "10.1.2.3", "PrincipalTag": { "team": "dev" } } }),
+    )
+    .unwrap();
+
+  assert_eq!(evaluator.evaluate(&request).unwrap().outcome, Outcome::Allow, "Session tags cannot be overridden by the context");
+}
+
+// STS - role chains pass on transitive tags and the source identity
+#[test]
+fn assume_role_chain() {
+  let trust = trust_policy(
+    "arn:aws:iam::111111111111:role/second",
+    json!({
+        "Effect": "Allow",
+        "Principal": "*",
+        "Action": "sts:AssumeRole",
+        "Condition": { "StringEquals": { "aws:PrincipalTag/project": "apollo" } }
+    }),
+  );
+
+  let first = trust_policy("arn:aws:iam::111111111111:role/first", json!({ "Effect": "Allow", "Principal": "*", "Action": "sts:AssumeRole" }))
+    .assume_role(
+      AssumeRole::new("arn:aws:iam::111111111111:user/alice", "arn:aws:iam::111111111111:role/first", "s1")
+        .unwrap()
+        .tag("project", "apollo", true)
+        .tag("team", "ops", false)
+        .source_identity("alice"),
+    )
+    .unwrap();
+
+  let second = trust.assume_role(first.assume_role("arn:aws:iam::111111111111:role/second", "s2").unwrap()).unwrap();
+
+  assert_eq!(second.arn, "arn:aws:sts::111111111111:assumed-role/second/s2");
+  assert_eq!(second.tags.get("project").map(String::as_str), Some("apollo"));
+  assert_eq!(second.tags.get("team"), None, "Non-transitive tags are not passed on");
+  assert_eq!(second.source_identity.as_deref(), Some("alice"));
+
+  let request = first.assume_role("arn:aws:iam::111111111111:role/second", "s2").unwrap().tag("project", "gemini", false);
+
+  assert!(matches!(trust.assume_role(request), Err(regoer::Error::InvalidSessionTag(_))));
+
+  let request = first.assume_role("arn:aws:iam::111111111111:role/second", "s2").unwrap().source_identity("bob");
+
+  assert!(matches!(trust.assume_role(request), Err(regoer::Error::InvalidSourceIdentity(_))));
+}
+
+// STS - trust policies only apply to their role
+#[test]
+fn trust_policies_scoped_to_roles() {
+  let trust = |principal: &str| json!({ "Version": "2012-10-17", "Statement": [{ "Effect": "Allow", "Principal": { "AWS": principal }, "Action": "sts:AssumeRole", "Resource": "*" }] });
+
+  let mut regoer = Regoer::default();
+
+  regoer
+    .add_trust_policy("arn:aws:iam::111111111111:role/ops", trust("arn:aws:iam::111111111111:user/alice").to_string().as_bytes())
+    .unwrap();
+  regoer
+    .add_trust_policy("arn:aws:iam::111111111111:role/dev", trust("arn:aws:iam::111111111111:user/bob").to_string().as_bytes())
+    .unwrap();
+
+  assert!(matches!(
+    regoer.add_trust_policy("arn:aws:iam::111111111111:user/alice", trust("*").to_string().as_bytes()),
+    Err(regoer::Error::InvalidRoleArn(_))
+  ));
+
+  let evaluator = regoer.compile().unwrap();
+
+  let request = |principal: &str, role: &str| AssumeRole::new(format!("arn:aws:iam::111111111111:user/{principal}"), format!("arn:aws:iam::111111111111:role/{role}"), principal).unwrap();
+
+  assert!(evaluator.assume_role(request("alice", "ops")).is_ok());
+  assert!(evaluator.assume_role(request("bob", "dev")).is_ok());
+  assert!(evaluator.assume_role(request("alice", "dev")).is_err(), "Trust policy of another role");
+  assert!(evaluator.assume_role(request("bob", "ops")).is_err(), "Trust policy of another role");
+}
+
+// STS - web identities assume roles as federated principals
+#[test]
+fn assume_role_with_web_identity() {
+  // Keys of identity providers, such as `accounts.google.com:aud`, cannot be
+  // parsed: claims are read from a custom `oidc:aud` key instead.
+  let evaluator = trust_policy(
+    "arn:aws:iam::111111111111:role/web",
+    json!({
+        "Effect": "Allow",
+        "Principal": { "Federated": "accounts.google.com" },
+        "Action": "sts:AssumeRoleWithWebIdentity",
+        "Condition": { "StringEquals": { "oidc:aud": "my-app" } }
+    }),
+  );
+
+  let request = |aud: &str| {
+    AssumeRole::with_web_identity("accounts.google.com", "arn:aws:iam::111111111111:role/web", "user")
+      .unwrap()
+      .context(json!({ "oidc": { "aud": aud } }))
+      .unwrap()
+  };
+
+  assert_eq!(evaluator.assume_role(request("my-app")).unwrap().arn, "arn:aws:sts::111111111111:assumed-role/web/user");
+  assert!(evaluator.assume_role(request("other-app")).is_err());
+  assert!(
+    evaluator
+      .assume_role(AssumeRole::new("accounts.google.com", "arn:aws:iam::111111111111:role/web", "user").unwrap())
+      .is_err(),
+    "Wrong action"
+  );
+}
+
+// Resource schemes - custom ARN partitions are matched like AWS ones
+#[test]
+fn custom_arn_partition() {
+  let evaluator = compile_policy(json!({
+      "Version": "2012-10-17",
+      "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "*", "Resource": "arn:acme:storage:*:111111111111:bucket/*" }]
+  }));
+
+  let request = |principal: &str, resource: &str| json!({ "principal": principal, "action": "storage:Get", "resource": resource });
+
+  assert!(evaluate(&evaluator, request("alice", "arn:acme:storage:eu:111111111111:bucket/key")));
+  assert!(!evaluate(&evaluator, request("alice", "arn:aws:storage:eu:111111111111:bucket/key")), "Other partition");
+  assert!(
+    !evaluate(&evaluator, request("arn:acme:iam::222222222222:user/bob", "arn:acme:storage:eu:111111111111:bucket/key")),
+    "Cross-account without a resource policy"
+  );
+}
+
+// Resource schemes - URN-style identifiers drive resource matching, ArnLike and accounts
+#[test]
+fn custom_resource_scheme() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "urn:acme:*:storage:bucket/*",
+          "Condition": { "ArnLike": { "acme:SourceUrn": "urn:acme:tenant-a:*:*" } }
+      }]
+  });
+
+  let mut regoer = Regoer::default();
+  regoer.add_resource_scheme(ResourceScheme::new("urn", 5).unwrap().account(2).unwrap());
+  regoer.add_policy(policy.to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+
+  let request = |principal: &str, resource: &str, source: &str| json!({ "principal": principal, "action": "storage:Get", "resource": resource, "acme": { "SourceUrn": source } });
+
+  assert!(evaluate(&evaluator, request("alice", "urn:acme:tenant-a:storage:bucket/key", "urn:acme:tenant-a:queue:jobs")));
+  assert!(
+    evaluate(&evaluator, request("alice", "urn:acme:tenant-a:storage:bucket/a:b", "urn:acme:tenant-a:queue:jobs:1")),
+    "The last segment absorbs colons"
+  );
+  assert!(
+    !evaluate(&evaluator, request("alice", "urn:acme:tenant-a:compute:bucket/key", "urn:acme:tenant-a:queue:jobs")),
+    "Other service"
+  );
+  assert!(
+    !evaluate(&evaluator, request("alice", "urn:acme:tenant-a:storage:bucket/key", "urn:acme:tenant-b:queue:jobs")),
+    "Other source tenant"
+  );
+  assert!(
+    !evaluate(&evaluator, request("alice", "urn:acme:tenant-a:storage:bucket/key", "urn:acme:tenant-a")),
+    "Source is not a URN"
+  );
+  assert!(
+    !evaluate(
+      &evaluator,
+      request("urn:acme:tenant-b:iam:user/bob", "urn:acme:tenant-a:storage:bucket/key", "urn:acme:tenant-a:queue:jobs")
+    ),
+    "Cross-account without a resource policy"
+  );
+  assert!(
+    evaluate(
+      &evaluator,
+      request("urn:acme:tenant-a:iam:user/alice", "urn:acme:tenant-a:storage:bucket/key", "urn:acme:tenant-a:queue:jobs")
+    ),
+    "Same account"
+  );
+
+  assert!(matches!(ResourceScheme::new("urn:acme", 5), Err(regoer::Error::InvalidResourceScheme(_))));
+  assert!(matches!(ResourceScheme::new("urn", 5).unwrap().account(5), Err(regoer::Error::InvalidResourceScheme(_))));
+}
+
+// Actions - action names are case-insensitive, resources are not
+#[test]
+fn case_insensitive_actions() {
+  let cases = [
+    (json!({ "Action": "s3:GetObject" }), false),
+    (json!({ "Action": "S3:GET*" }), false),
+    (json!({ "Action": ["s3:getobject", "s3:PutObject"] }), true),
+    (json!({ "Action": ["s3:get*", "ec2:*"] }), false),
+    (json!({ "NotAction": "s3:putobject" }), false),
+    (json!({ "NotAction": ["S3:Put*", "s3:Delete*"] }), false),
+  ];
+
+  let request = |action: &str, resource: &str| json!({ "principal": "alice", "action": action, "resource": resource });
+
+  for (scope, put) in cases {
+    let mut statement = json!({ "Effect": "Allow", "Principal": "*", "Resource": "arn:aws:s3:::bucket/Key" });
+    statement.as_object_mut().unwrap().extend(scope.as_object().unwrap().clone());
+
+    let evaluator = compile_policy(json!({ "Version": "2012-10-17", "Statement": [statement] }));
+
+    for action in ["s3:GetObject", "s3:getobject", "S3:GETOBJECT"] {
+      assert!(evaluate(&evaluator, request(action, "arn:aws:s3:::bucket/Key")), "{scope} should match {action}");
+    }
+
+    assert_eq!(evaluate(&evaluator, request("s3:PUTOBJECT", "arn:aws:s3:::bucket/Key")), put, "{scope} on s3:PUTOBJECT");
+    assert!(
+      !evaluate(&evaluator, request("s3:GetObject", "arn:aws:s3:::bucket/key")),
+      "{scope} should match resources case-sensitively"
+    );
+  }
+}
+
+// Condition keys - key names are case-insensitive, values are not
+#[test]
+fn case_insensitive_condition_keys() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": "*",
+          "Action": "*",
+          "Resource": "arn:aws:s3:::${aws:UserName}/*",
+          "Condition": {
+              "IpAddress": { "aws:SourceIp": "10.0.0.0/8" },
+              "StringEquals": { "aws:RequestTag/CostCenter": "Finance" },
+              "ForAllValues:StringEquals": { "aws:TagKeys": ["CostCenter"] }
+          }
+      }]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let request = |aws: serde_json::Value| json!({ "principal": "alice", "action": "s3:PutObject", "resource": "arn:aws:s3:::alice/key", "aws": aws });
+
+  assert!(evaluate(
+    &evaluator,
+    request(json!({ "SourceIp": "10.1.2.3", "RequestTag": { "CostCenter": "Finance" }, "TagKeys": ["CostCenter"], "UserName": "alice" }))
+  ));
+  assert!(evaluate(
+    &evaluator,
+    request(json!({ "sourceip": "10.1.2.3", "requesttag": { "costcenter": "Finance" }, "tagkeys": ["CostCenter"], "username": "alice" }))
+  ));
+  assert!(evaluate(
+    &evaluator,
+    request(json!({ "SOURCEIP": "10.1.2.3", "RequestTag": { "COSTCENTER": "Finance" }, "TagKeys": ["CostCenter"], "userName": "alice" }))
+  ));
+  assert!(
+    !evaluate(
+      &evaluator,
+      request(json!({ "SourceIp": "10.1.2.3", "RequestTag": { "CostCenter": "finance" }, "TagKeys": ["CostCenter"], "UserName": "alice" }))
+    ),
+    "Tag values are case-sensitive"
+  );
+  assert!(
+    !evaluate(
+      &evaluator,
+      request(json!({ "SourceIp": "10.1.2.3", "RequestTag": { "CostCenter": "Finance" }, "TagKeys": ["costcenter"], "UserName": "alice" }))
+    ),
+    "aws:TagKeys values are case-sensitive"
+  );
+
+  let input = request(json!({ "SourceIp": "10.1.2.3", "RequestTag": { "CostCenter": "Finance", "costcenter": "Other" }, "UserName": "alice" }));
+
+  assert!(matches!(evaluator.evaluate(&input), Err(regoer::Error::DuplicateInputKey(key)) if key == "costcenter"));
+
+  let input = json!({ "principal": { "type": "AWS", "id": "Alice" }, "action": "s3:PutObject", "resource": "arn:aws:s3:::Alice/key", "aws": { "SourceIp": "10.1.2.3", "RequestTag": { "CostCenter": "Finance" }, "TagKeys": ["CostCenter"], "UserName": "Alice" } });
+
+  assert!(evaluate(&evaluator, input), "Principal and values keep their case");
+}
+
+// Version - policy variables are only interpolated from version 2012-10-17
+#[test]
+fn legacy_version_literal_variables() {
+  let policy = |version: Option<&str>| {
+    let mut policy = json!({
+        "Statement": [{
+            "Effect": "Allow",
+            "Principal": "*",
+            "Action": "s3:GetObject",
+            "Resource": "arn:aws:s3:::bucket/${aws:username}/*",
+            "Condition": { "StringEquals": { "s3:prefix": "${aws:username}" } }
+        }]
+    });
+
+    if let Some(version) = version {
+      policy["Version"] = json!(version);
+    }
+
+    compile_policy(policy)
+  };
+
+  let request = |resource: &str, prefix: &str| json!({ "principal": "alice", "action": "s3:GetObject", "resource": resource, "aws": { "username": "alice" }, "s3": { "prefix": prefix } });
+
+  let evaluator = policy(Some("2012-10-17"));
+
+  assert!(evaluate(&evaluator, request("arn:aws:s3:::bucket/alice/key", "alice")));
+  assert!(!evaluate(&evaluator, request("arn:aws:s3:::bucket/${aws:username}/key", "${aws:username}")));
+
+  for version in [Some("2008-10-17"), None] {
+    let evaluator = policy(version);
+
+    assert!(!evaluate(&evaluator, request("arn:aws:s3:::bucket/alice/key", "alice")), "{version:?} should not interpolate");
+    assert!(
+      evaluate(&evaluator, request("arn:aws:s3:::bucket/${aws:username}/key", "${aws:username}")),
+      "{version:?} should match literals"
+    );
+  }
+}
+
+// Version - unknown versions are rejected
+#[test]
+fn unsupported_version() {
+  let policy = json!({
+      "Version": "2020-01-01",
+      "Statement": [{ "Effect": "Allow", "Principal": "*", "Action": "*", "Resource": "*" }]
+  });
+
+  let mut regoer = Regoer::default();
+
+  let err = regoer.add_policy(policy.to_string().as_bytes()).unwrap_err();
+
+  assert!(matches!(err.inner(), regoer::Error::UnsupportedVersion(_)));
+  assert_eq!(err.location().and_then(|location| location.line), Some(1));
+}
+
+// Explain - every scope and condition of every statement is reported
+#[test]
+fn explain_statements() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Statement": [
+          {
+              "Sid": "ReadBucket",
+              "Effect": "Allow",
+              "Principal": { "AWS": "alice" },
+              "Action": "s3:Get*",
+              "Resource": "arn:aws:s3:::bucket/*",
+              "Condition": {
+                  "StringEquals": { "aws:PrincipalTag/team": ["storage", "ops"] },
+                  "IpAddress": { "aws:SourceIp": "10.0.0.0/8" }
+              }
+          },
+          {
+              "Effect": "Deny",
+              "Principal": "*",
+              "NotAction": "s3:*",
+              "Resource": "*"
+          }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+
+  let explanation = evaluator
+    .explain(&json!({ "principal": "alice", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key", "aws": { "PrincipalTag": { "team": "storage" }, "SourceIp": "192.168.1.1" } }))
+    .unwrap();
+
+  assert_eq!(explanation.decision.outcome, Outcome::ImplicitDeny);
+  assert_eq!(explanation.policies.len(), 1);
+
+  let policy = &explanation.policies[0];
+
+  assert_eq!((policy.policy, policy.kind, policy.applies), (0, PolicyType::Identity, true));
+  assert_eq!(policy.statements.len(), 2);
+
+  let read = &policy.statements[0];
+
+  assert_eq!((read.statement, read.sid.as_deref(), read.effect), (0, Some("ReadBucket"), Effect::Allow));
+  assert_eq!((read.principal, read.action, read.resource), (true, true, true));
+  assert_eq!(read.matched(), false, "Source IP condition failed");
+
+  let conditions: Vec<_> = read.conditions.iter().map(|c| (c.operator.as_str(), c.key.as_str(), c.values.clone(), c.passed)).collect();
+
+  assert_eq!(
+    conditions,
+    vec![
+      ("IpAddress", "aws:SourceIp", vec![json!("10.0.0.0/8")], false),
+      ("StringEquals", "aws:PrincipalTag/team", vec![json!("storage"), json!("ops")], true),
+    ]
+  );
+
+  let deny = &policy.statements[1];
+
+  assert_eq!((deny.statement, deny.sid.as_deref(), deny.effect), (1, None, Effect::Deny));
+  assert_eq!((deny.principal, deny.action, deny.resource), (true, false, true), "NotAction excludes s3 actions");
+  assert!(deny.conditions.is_empty());
+
+  let explanation = evaluator
+    .explain(&json!({ "principal": "alice", "action": "s3:GetObject", "resource": "arn:aws:s3:::bucket/key", "aws": { "PrincipalTag": { "team": "ops" }, "SourceIp": "10.0.0.1" } }))
+    .unwrap();
+
+  assert_eq!(explanation.decision.outcome, Outcome::Allow);
+  assert!(explanation.policies[0].statements[0].matched());
+  assert_eq!(
+    explanation.decision.statements,
+    vec![MatchedStatement {
+      policy: 0,
+      statement: 0,
+      sid: Some("ReadBucket".into())
+    }]
+  );
+}
+
+// Explain - service control policies outside the organization path do not apply
+#[test]
+fn explain_policy_applicability() {
+  let mut regoer = organization();
+
+  regoer.add_service_control_policy("ou-sandbox", statement("Allow", "ec2:*").to_string().as_bytes()).unwrap();
+
+  let evaluator = regoer.compile().unwrap();
+  let explanation = evaluator
+    .explain(&json!({ "principal": "arn:aws:iam::111111111111:user/alice", "action": "s3:GetObject", "resource": "*" }))
+    .unwrap();
+
+  assert_eq!(explanation.decision.outcome, Outcome::Allow);
+
+  let applies: Vec<_> = explanation.policies.iter().map(|policy| (policy.policy, policy.kind, policy.applies)).collect();
+
+  assert_eq!(applies, vec![(0, PolicyType::Identity, true), (1, PolicyType::ServiceControl, false)]);
+}
+
+// Source maps - generated rules are traced back to the policy document
+#[test]
+fn source_map() {
+  let policy = json!({
+      "Version": "2012-10-17",
+      "Id": "Storage",
+      "Statement": [
+          { "Effect": "Deny", "NotAction": "s3:*", "Resource": "*" },
+          {
+              "Sid": "ReadTeam",
+              "Effect": "Allow",
+              "Action": "s3:GetObject",
+              "Resource": "arn:aws:s3:::bucket/*",
+              "Condition": { "StringEquals": { "aws:PrincipalTag/team": "storage" } }
+          }
+      ]
+  });
+
+  let evaluator = compile_policy(policy);
+  let policy = &evaluator.rego()[0];
+  let rego = policy.serialize().unwrap();
+  let source_map = &evaluator.source_maps()[0];
+
+  assert_eq!(policy.name(), Some("Storage"));
+  assert_eq!(source_map, &policy.source_map().unwrap());
+  assert_eq!(source_map.file(), "regoer.policies.p0.rego");
+
+  let line = |needle: &str| rego.lines().position(|line| line.contains(needle)).unwrap() + 1;
+
+  let location = source_map.locate(line(r#""storage" == input.aws.principaltag.team"#)).unwrap();
+
+  assert_eq!((location.policy, location.statement, location.sid.as_deref()), (0, 1, Some("ReadTeam")));
+  assert_eq!(location.pointer, "/Statement/1/Condition/StringEquals/aws:PrincipalTag~1team");
+  assert_eq!(location.to_string(), "policy Storage, statement ReadTeam, Condition.StringEquals.aws:PrincipalTag/team");
+
+  let location = source_map.locate(line(r#"not glob.match("s3:*""#)).unwrap();
+
+  assert_eq!(location.to_string(), "policy Storage, statement 0, NotAction");
+  assert_eq!(source_map.locate(line("statement_0_principal := true")).unwrap().pointer, "/Statement/0");
+  assert_eq!(source_map.locate(line("package ")), None);
+
+  let message = format!("\n--> regoer.policies.p0.rego:{}:3\nerror: expecting expression", line(r#""storage" == input.aws.principaltag.team"#));
+
+  assert_eq!(source_map.resolve(&message).unwrap().sid.as_deref(), Some("ReadTeam"));
+  assert_eq!(source_map.resolve("--> regoer.policies.p1.rego:12:3"), None);
+}
+
+// Errors - parse and transpile errors point to the offending element of the document
+#[test]
+fn error_locations() {
+  let document = r#"{
+  "Version": "2012-10-17",
+  "Statement": [
+    { "Effect": "Allow", "Action": "*", "Resource": "*" },
+    {
+      "Sid": "Numeric",
+      "Effect": "Deny",
+      "Action": "s3:GetObject",
+      "Resource": "*",
+      "Condition": { "NumericLessThan": { "s3:max-keys": "abc" } }
+    }
+  ]
+}"#;
+
+  let mut regoer = Regoer::default();
+  let err = regoer.add_policy(document.as_bytes()).unwrap_err();
+  let location = err.location().unwrap();
+
+  assert!(matches!(err.inner(), regoer::Error::InvalidType(_, _)));
+  assert_eq!((location.statement, location.sid.as_deref()), (Some(1), Some("Numeric")));
+  assert_eq!((location.operator.as_deref(), location.key.as_deref()), (Some("NumericLessThan"), Some("s3:max-keys")));
+  assert_eq!(location.pointer.as_deref(), Some("/Statement/1/Condition/NumericLessThan/s3:max-keys"));
+  assert_eq!(
+    err.to_string(),
+    [
+      "expected number, found 'abc'",
+      "--> line 10, column 43, statement 1 (Numeric), condition NumericLessThan s3:max-keys",
+      "   |",
+      r#"10 |       "Condition": { "NumericLessThan": { "s3:max-keys": "abc" } }"#,
+      "   |                                           ^",
+    ]
+    .join("\n")
+  );
+
+  let mut regoer = Regoer::default();
+  let err = regoer.add_policy(document.replace(r#""Sid": "Numeric","#, r#""Sid": "Numeric""#).as_bytes()).unwrap_err();
+  let location = err.location().unwrap();
+
+  assert!(matches!(err, regoer::Error::SyntaxError(_, _)));
+  assert_eq!((location.line, location.column), (Some(7), Some(7)));
+  assert_eq!(location.snippet(), Some(r#"      "Effect": "Deny","#));
+
+  let err = regoer.add_policy(document.replace(r#""Deny""#, r#""Dney""#).as_bytes()).unwrap_err();
+
+  let location = err.location().unwrap();
+
+  assert_eq!((location.statement, location.sid.as_deref(), location.line), (Some(1), Some("Numeric"), Some(5)));
+
+  let err = regoer.add_policy(document.replace("NumericLessThan", "ForAnyValue:FooIfExists").as_bytes()).unwrap_err();
+  let location = err.location().unwrap();
+
+  assert!(matches!(err.inner(), regoer::Error::UnsupportedFunction(operator) if operator == "ForAnyValue:FooIfExists"));
+  assert_eq!(location.pointer.as_deref(), Some("/Statement/1/Condition/ForAnyValue:FooIfExists/s3:max-keys"));
+  assert_eq!((location.line, location.column), (Some(10), Some(51)));
+  assert_eq!(
+    err.to_string().lines().next(),
+    Some("unsupported function ForAnyValue:FooIfExists"),
+    "Unknown operators are reported by their name"
+  );
+}
+
+// Validation - every statement and condition is checked, and all errors and warnings are reported
+#[test]
+fn validate_policy() {
+  let document = r#"{
+  "Statement": [
+    {
+      "Sid": "Conditions",
+      "Effect": "Allow",
+      "Action": "s3:GetObject",
+      "Resource": "arn:aws:s3:::${aws:username}/*",
+      "Condition": { "NumericLessThan": { "s3:max-keys": "abc" }, "Bool": { "aws:SecureTransport": "maybe" } }
+    },
+    { "Sid": "Effect", "Effect": "Dney", "Action": "s3:GetObject", "Resource": "*" }
+  ]
+}"#;
+
+  let diagnostics = Regoer::validate(document.as_bytes());
+  let found: Vec<_> = diagnostics
+    .iter()
+    .map(|diagnostic| {
+      let location = diagnostic.location.as_ref().unwrap();
+      (diagnostic.severity, location.statement, location.key.as_deref(), location.line)
+    })
+    .collect();
+
+  assert_eq!(
+    found,
+    vec![
+      (Severity::Error, Some(0), Some("aws:SecureTransport"), Some(8)),
+      (Severity::Error, Some(0), Some("s3:max-keys"), Some(8)),
+      (Severity::Error, Some(1), None, Some(10)),
+      (Severity::Warning, None, None, Some(1)),
+      (Severity::Warning, None, None, Some(7)),
+    ]
+  );
+
+  assert_eq!(diagnostics[1].message, "expected number, found 'abc'");
+  assert_eq!(diagnostics[3].message, "missing Version element, the policy is evaluated as version 2008-10-17");
+  let warning = diagnostics[4].to_string();
+  assert!(warning.starts_with("warning: policy variables are only interpolated from version 2012-10-17\n--> line 7, column 7"));
+
+  // Adding the policy reports the first of these errors
+  let err = Regoer::default().add_policy(document.as_bytes()).unwrap_err();
+  let location = err.location().unwrap();
+  assert_eq!((location.statement, location.key.as_deref()), (Some(0), Some("aws:SecureTransport")));
+
+  let document = document.replace(r#""Dney""#, r#""Deny""#).replace(r#""abc""#, "10").replace(r#""maybe""#, "true");
+  let diagnostics = Regoer::validate(format!("{{\"Version\": \"2012-10-17\",{}", &document[1..]).as_bytes());
+
+  assert_eq!(diagnostics, vec![]);
+
+  let diagnostics = Regoer::validate(format!("{{\"Version\": 2012,{}", &document[1..]).as_bytes());
+  let messages: Vec<_> = diagnostics.iter().map(|diagnostic| (diagnostic.severity, diagnostic.message.as_str())).collect();
+
+  assert_eq!(messages, vec![(Severity::Error, "unsupported policy version 2012")], "Versions which are not strings are not missing");
+}
+
+// Lint - security and quality findings are reported with their code, severity and location
+#[test]
+fn lint_policy() {
+  let document = r#"{
+  "Version": "2012-10-17",
+  "Statement": [
+    { "Sid": "Admin", "Effect": "Allow", "Action": "*", "Resource": "*" },
+    { "Sid": "Public", "Effect": "Allow", "Principal": "*", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::public/*" },
+    { "Sid": "Everything", "Effect": "Allow", "NotAction": "iam:*", "Resource": "*" },
+    {
+      "Sid": "Objects",
+      "Effect": "Allow",
+      "Action": ["s3:GetObject", "s3:PutObject"],
+      "Resource": ["arn:aws:s3:::bucket/*", "arn:aws:s3:bucket"],
+      "Condition": { "StringEquals": { "ec2:Region": "eu-west-1", "aws:SourceIpAddress": "10.0.0.1", "s3:prefix": "home/" } }
+    },
+    { "Sid": "Objects", "Effect": "Allow", "Action": "s3:getobject", "Resource": "arn:aws:s3:::bucket/key" },
+    { "Effect": "Deny", "Action": "s3:*", "Resource": "arn:aws:s3:::secret/*" },
+    { "Effect": "Allow", "Action": "s3:Get*", "Resource": "arn:aws:s3:::secret/key" }
+  ]
+}"#;
+
+  let findings = Regoer::lint(document.as_bytes()).unwrap();
+  let found: Vec<_> = findings.iter().map(|finding| (finding.code, finding.location.statement, finding.location.line)).collect();
+
+  assert_eq!(
+    found,
+    vec![
+      (FindingCode::AllowAll, Some(0), Some(4)),
+      (FindingCode::PublicPrincipal, Some(1), Some(5)),
+      (FindingCode::RedundantStatement, Some(1), Some(5)),
+      (FindingCode::AllowNotAction, Some(2), Some(6)),
+      (FindingCode::UnknownConditionKey, Some(3), Some(12)),
+      (FindingCode::UnknownConditionKey, Some(3), Some(12)),
+      (FindingCode::MalformedArn, Some(3), Some(11)),
+      (FindingCode::RedundantStatement, Some(3), Some(7)),
+      (FindingCode::RedundantStatement, Some(4), Some(14)),
+      (FindingCode::DuplicateSid, Some(4), Some(14)),
+      (FindingCode::ShadowedStatement, Some(6), Some(16)),
+    ]
+  );
+
+  assert_eq!(findings[0].severity, FindingSeverity::Security);
+  assert_eq!(findings[4].severity, FindingSeverity::Suggestion);
+  assert_eq!(findings[5].location.key.as_deref(), Some("ec2:Region"));
+  assert_eq!(findings[5].location.pointer.as_deref(), Some("/Statement/3/Condition/StringEquals/ec2:Region"));
+  assert_eq!(
+    findings[6].to_string(),
+    [
+      "error[MALFORMED_ARN]: arn:aws:s3:bucket is not shaped as arn:partition:service:region:account:resource",
+      "--> line 11, column 45, statement 3 (Objects)",
+      "   |",
+      r#"11 |       "Resource": ["arn:aws:s3:::bucket/*", "arn:aws:s3:bucket"],"#,
+      "   |                                             ^",
+    ]
+    .join("\n")
+  );
+
+  let findings = Regoer::lint(
+    json!({
+      "Version": "2012-10-17",
+      "Statement": [
+        { "Sid": "Read", "Effect": "Allow", "Action": "s3:GetObject", "Resource": "arn:aws:s3:::bucket/*", "Condition": { "Bool": { "aws:SecureTransport": "true" } } },
+        { "Sid": "Write", "Effect": "Allow", "Action": "s3:PutObject", "Resource": "arn:aws:s3:::bucket/*", "Condition": { "StringLike": { "s3:x-amz-acl": "private" } } },
+        { "Sid": "Secret", "Effect": "Deny", "Action": "s3:*", "Resource": "arn:aws:s3:::bucket/secret/*" }
+      ]
+    })
+    .to_string()
+    .as_bytes(),
+  )
+  .unwrap();
+
+  assert_eq!(findings, vec![]);
+}
+
+// Lint - principals are checked for public access and malformed ARNs
+#[test]
+fn lint_principals() {
+  let document = json!({
+    "Version": "2012-10-17",
+    "Statement": [
+      { "Effect": "Allow", "NotPrincipal": { "AWS": "arn:aws:iam::111111111111:root" }, "Action": "s3:GetObject", "Resource": "arn:aws:s3:::bucket/*" },
+      { "Effect": "Allow", "Principal": { "AWS": ["arn:aws:iam::111111111111:root", "arn:aws:iam:bob"] }, "Action": "sqs:SendMessage", "Resource": "*" },
+      { "Effect": "Allow", "Principal": "*", "Action": "sqs:SendMessage", "Resource": "*", "Condition": { "StringEquals": { "kms:ViaService": "sqs.eu-west-1.amazonaws.com" } } }
+    ]
+  });
+
+  let findings = Regoer::lint(document.to_string().as_bytes()).unwrap();
+  let found: Vec<_> = findings.iter().map(|finding| (finding.code, finding.severity, finding.location.pointer.as_deref())).collect();
+
+  assert_eq!(
+    found,
+    vec![
+      (FindingCode::PublicPrincipal, FindingSeverity::Security, Some("/Statement/0/NotPrincipal")),
+      (FindingCode::MalformedArn, FindingSeverity::Error, Some("/Statement/1/Principal/AWS/1")),
+      (
+        FindingCode::UnknownConditionKey,
+        FindingSeverity::Suggestion,
+        Some("/Statement/2/Condition/StringEquals/kms:ViaService")
+      ),
+    ]
+  );
+}
+
+// Escaping - values substituted for policy variables in patterns are matched literally
+#[test]
+fn glob_metacharacters_in_variables() {
+  let evaluator = compile_policy(json!({
+      "Version": "2012-10-17",
+      "Statement": [{
+          "Effect": "Allow",
+          "Principal": {"AWS": "testuser"},
+          "Action": "s3:GetObject",
+          "Resource": "arn:aws:s3:::home/${aws:username}/*",
+          "Condition": { "StringLike": { "s3:prefix": "${aws:username}/*" } }
+      }]
+  }));
+
+  let request = |username: &str, folder: &str| json!({ "principal": "testuser", "action": "s3:GetObject", "resource": format!("arn:aws:s3:::home/{folder}/key"), "aws": { "username": username }, "s3": { "prefix": format!("{folder}/key") } });
+
+  assert!(evaluate(&evaluator, request("alice", "alice")));
+  assert!(evaluate(&evaluator, request("*", "*")));
+  assert!(evaluate(&evaluator, request(r"[a]\{b}", r"[a]\{b}")));
+
+  assert!(!evaluate(&evaluator, request("*", "bob")), "Wildcards in values are literal");
+  assert!(!evaluate(&evaluator, request("?ob", "bob")), "Wildcards in values are literal");
+  assert!(!evaluate(&evaluator, request("[ab]", "a")), "Brackets in values are not a character class");
+}